@@ -0,0 +1,216 @@
+//! Study area shared by every stage of the pipeline, read from `area.toml` and command-line flags.
+
+pub mod parse;
+
+/// Where the study area polygon comes from.
+#[derive(Debug, Clone, serde::Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Source {
+    /// `[min_lon, min_lat, max_lon, max_lat]`
+    Bbox([f64; 4]),
+    /// GeoJSON file holding a Polygon or MultiPolygon, or features of them, relative to the config
+    /// file when read from one
+    Geojson(std::path::PathBuf),
+    /// municipality code, `N03_007` of the `city` table
+    City(String),
+}
+
+#[derive(Debug, Clone, serde::Deserialize)]
+pub struct Config {
+    pub area: Source,
+    /// tile zoom level per crawled layer
+    #[serde(default)]
+    pub zoom: std::collections::BTreeMap<String, u8>,
+}
+
+/// Study area flags, each one overriding the config file.
+#[derive(Debug, Clone, clap::Args)]
+#[group(id = "area")]
+pub struct Args {
+    /// study area config file, `area.toml` in the working directory or its nearest parent by default
+    #[arg(long)]
+    pub config: Option<std::path::PathBuf>,
+    /// bounding box as `min_lon,min_lat,max_lon,max_lat`
+    #[arg(long, value_delimiter = ',', num_args = 4, conflicts_with_all = ["geojson", "city"])]
+    pub bbox: Option<Vec<f64>>,
+    /// GeoJSON file of the area polygon
+    #[arg(long, conflicts_with = "city")]
+    pub geojson: Option<std::path::PathBuf>,
+    /// municipality code in the `city` table
+    #[arg(long)]
+    pub city: Option<String>,
+}
+
+impl Args {
+    pub fn config(&self) -> Config {
+        let source = if let Some(bbox) = &self.bbox {
+            Some(Source::Bbox([bbox[0], bbox[1], bbox[2], bbox[3]]))
+        } else if let Some(path) = &self.geojson {
+            Some(Source::Geojson(path.clone()))
+        } else {
+            self.city.clone().map(Source::City)
+        };
+
+        // a config file passed by the flag has to be read, one found may be skipped for the flags
+        let file = match &self.config {
+            Some(path) => match std::fs::read_to_string(path) {
+                Ok(text) => Some((path.clone(), Ok(text))),
+                Err(err) => panic!("failed to read {} ({})", path.display(), err),
+            },
+            None => {
+                let dir = std::env::current_dir().expect("failed to get working directory");
+                find_config(&dir).map(|path| {
+                    let text = std::fs::read_to_string(&path);
+                    (path, text)
+                })
+            }
+        };
+
+        match (file, source) {
+            (Some((path, Ok(text))), source) => {
+                let mut config: Config = toml::from_str(&text).expect("failed to parse config");
+                if let Source::Geojson(geojson) = &mut config.area {
+                    let dir = path.parent().unwrap_or(std::path::Path::new(""));
+                    *geojson = dir.join(&*geojson);
+                }
+                if let Some(source) = source {
+                    config.area = source;
+                }
+                config
+            }
+            (_, Some(source)) => Config {
+                area: source,
+                zoom: Default::default(),
+            },
+            (Some((path, Err(err))), None) => {
+                panic!("failed to read {} ({})", path.display(), err);
+            }
+            (None, None) => {
+                panic!("no area.toml in the working directory or its parents, pass --config");
+            }
+        }
+    }
+}
+
+/// `area.toml` in `dir` or the nearest of its parents holding one, so every tool finds the one at
+/// the repository root from its own directory.
+pub fn find_config(dir: &std::path::Path) -> Option<std::path::PathBuf> {
+    dir.ancestors()
+        .map(|dir| dir.join("area.toml"))
+        .find(|path| path.is_file())
+}
+
+impl Source {
+    /// Resolve the area polygon in EPSG:6668, the `city` source queries postgresql.
+    pub async fn load(&self) -> geo::MultiPolygon<f64> {
+        match self {
+            Source::Bbox([x0, y0, x1, y1]) => {
+                let rect = geo::Rect::new(
+                    geo::coord! { x: *x0, y: *y0 },
+                    geo::coord! { x: *x1, y: *y1 },
+                );
+                geo::MultiPolygon::new(vec![rect.to_polygon()])
+            }
+            Source::Geojson(path) => {
+                let text = std::fs::read_to_string(path).expect("failed to read geojson");
+                let geojson = text
+                    .parse::<geojson::GeoJson>()
+                    .expect("failed to parse geojson");
+                polygons(geojson)
+            }
+            Source::City(code) => {
+                #[rustfmt::skip]
+                let pool = sqlx::postgres::PgPoolOptions::new()
+                    .connect("postgres://postgres:0@localhost/postgres")
+                    .await
+                    .expect("failed to connect postgresql");
+
+                #[rustfmt::skip]
+                let (text,): (Option<String>,) = sqlx::query_as("SELECT ST_AsGeoJSON(ST_Union(geom)) FROM city WHERE \"N03_007\" = $1")
+                    .bind(code)
+                    .fetch_one(&pool)
+                    .await
+                    .unwrap();
+
+                let text = text.unwrap_or_else(|| panic!("no city of code {}", code));
+                let geojson = text
+                    .parse::<geojson::GeoJson>()
+                    .expect("failed to parse geojson");
+                polygons(geojson)
+            }
+        }
+    }
+}
+
+/// Collect every Polygon and MultiPolygon of the GeoJSON.
+fn polygons(geojson: geojson::GeoJson) -> geo::MultiPolygon<f64> {
+    let geometries = match geojson {
+        geojson::GeoJson::Geometry(geometry) => vec![geometry],
+        geojson::GeoJson::Feature(feature) => feature.geometry.into_iter().collect(),
+        geojson::GeoJson::FeatureCollection(collection) => collection
+            .features
+            .into_iter()
+            .filter_map(|feature| feature.geometry)
+            .collect(),
+    };
+
+    let mut polygons = vec![];
+    for geometry in geometries {
+        match geo::Geometry::<f64>::try_from(geometry) {
+            Ok(geo::Geometry::Polygon(polygon)) => polygons.push(polygon),
+            Ok(geo::Geometry::MultiPolygon(multi)) => polygons.extend(multi),
+            Ok(_) => panic!("area geometry is not a polygon"),
+            Err(err) => panic!("failed to convert geojson ({})", err),
+        }
+    }
+
+    geo::MultiPolygon::new(polygons)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn args(config: Option<&str>) -> Args {
+        Args {
+            config: config.map(std::path::PathBuf::from),
+            bbox: Some(vec![137.0, 36.0, 137.1, 36.1]),
+            geojson: None,
+            city: None,
+        }
+    }
+
+    #[test]
+    #[should_panic(expected = "failed to read")]
+    fn fails_on_an_unreadable_config_flag_even_with_an_area_flag() {
+        args(Some("/nonexistent/area.toml")).config();
+    }
+
+    #[test]
+    fn overrides_the_config_area_by_the_flag() {
+        let dir = std::env::temp_dir().join(format!("area-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("area.toml");
+        std::fs::write(
+            &path,
+            "area = { geojson = \"area.geojson\" }\n[zoom]\nrdcl = 15\n",
+        )
+        .unwrap();
+
+        let config = args(path.to_str()).config();
+        assert!(matches!(config.area, Source::Bbox(_)));
+        assert_eq!(config.zoom.get("rdcl"), Some(&15));
+
+        let config = Args {
+            bbox: None,
+            ..args(path.to_str())
+        }
+        .config();
+        match config.area {
+            Source::Geojson(geojson) => assert_eq!(geojson, dir.join("area.geojson")),
+            area => panic!("unexpected area {:?}", area),
+        }
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
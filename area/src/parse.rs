@@ -0,0 +1,62 @@
+//! Value parsers for the numeric flags of every tool, rejecting what would hang, divide by zero or
+//! break a cost bound further down.
+
+/// A number greater than zero.
+pub fn positive(s: &str) -> Result<f64, String> {
+    match s.parse::<f64>() {
+        Ok(value) if value > 0.0 && value.is_finite() => Ok(value),
+        Ok(_) => Err(format!("{} is not greater than zero", s)),
+        Err(err) => Err(err.to_string()),
+    }
+}
+
+/// A number of zero or more.
+pub fn non_negative(s: &str) -> Result<f64, String> {
+    match s.parse::<f64>() {
+        Ok(value) if value >= 0.0 && value.is_finite() => Ok(value),
+        Ok(_) => Err(format!("{} is negative", s)),
+        Err(err) => Err(err.to_string()),
+    }
+}
+
+/// A number of one or more, for factors which must not lower a cost.
+pub fn at_least_one(s: &str) -> Result<f64, String> {
+    match s.parse::<f64>() {
+        Ok(value) if value >= 1.0 && value.is_finite() => Ok(value),
+        Ok(_) => Err(format!("{} is less than one", s)),
+        Err(err) => Err(err.to_string()),
+    }
+}
+
+/// A whole number greater than zero.
+pub fn count(s: &str) -> Result<usize, String> {
+    match s.parse::<usize>() {
+        Ok(0) => Err(format!("{} is not greater than zero", s)),
+        Ok(value) => Ok(value),
+        Err(err) => Err(err.to_string()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rejects_zero_only_when_positive() {
+        assert_eq!(positive("0.5"), Ok(0.5));
+        assert!(positive("0").is_err());
+        assert_eq!(non_negative("0"), Ok(0.0));
+        assert!(non_negative("-1").is_err());
+        assert_eq!(count("1"), Ok(1));
+        assert!(count("0").is_err());
+        assert_eq!(at_least_one("1"), Ok(1.0));
+        assert!(at_least_one("0.9").is_err());
+    }
+
+    #[test]
+    fn rejects_what_is_not_a_finite_number() {
+        assert!(positive("inf").is_err());
+        assert!(non_negative("NaN").is_err());
+        assert!(positive("fast").is_err());
+    }
+}
@@ -0,0 +1,19 @@
+use clap::Parser;
+
+/// Crawl a GSI vector tile layer of `tile::layers` into its tables.
+#[derive(Debug, clap::Parser)]
+struct Cli {
+    /// layer name, `rdcl` or `fgd`
+    #[arg(long, value_parser = tile::layers::find)]
+    layer: tile::TileLayer,
+    #[command(flatten)]
+    area: area::Args,
+    #[command(flatten)]
+    crawl: tile::Args,
+}
+
+#[tokio::main]
+async fn main() {
+    let cli = Cli::parse();
+    tile::run(cli.layer, &cli.area, &cli.crawl).await;
+}
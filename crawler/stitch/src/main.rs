@@ -0,0 +1,85 @@
+use clap::Parser;
+
+/// Merge rdcl fragments split at tile boundaries into the `road` table.
+#[derive(Debug, clap::Parser)]
+struct Cli {
+    /// endpoint snapping grid in degrees
+    #[arg(long, default_value_t = tile::stitch::Options::default().snap, value_parser = area::parse::positive)]
+    snap: f64,
+    /// largest heading change in degrees at a merged point
+    #[arg(long, default_value_t = tile::stitch::Options::default().max_angle)]
+    max_angle: f64,
+}
+
+#[tokio::main]
+async fn main() {
+    let cli = Cli::parse();
+
+    #[rustfmt::skip]
+    let pool = sqlx::postgres::PgPoolOptions::new()
+        .connect("postgres://postgres:0@localhost/postgres")
+        .await
+        .expect("failed to connect postgresql");
+
+    // fragments of one road link share their attributes
+    #[rustfmt::skip]
+    let rows: Vec<(i32, Option<String>, String)> = sqlx::query_as("SELECT id, properties::Text, ST_AsGeoJSON(geom) FROM rdcl ORDER BY id")
+        .fetch_all(&pool)
+        .await
+        .unwrap();
+
+    let mut ids = vec![];
+    let mut lines = vec![];
+    for (id, properties, geom) in rows {
+        let geometry = geom.parse::<geojson::Geometry>().unwrap();
+        let line = geo::LineString::<f64>::try_from(geometry.value).unwrap();
+        ids.push(id);
+        lines.push((line, properties));
+    }
+
+    let options = tile::stitch::Options {
+        snap: cli.snap,
+        max_angle: cli.max_angle,
+    };
+    let (stitched, stats) = tile::stitch::stitch(&lines, &options);
+
+    println!(
+        "[stitch stats] input: {}, duplicates: {}, merges: {}, output: {}",
+        stats.input,
+        stats.duplicates,
+        stats.merges,
+        stitched.len()
+    );
+
+    let rdcls = stitched.iter().map(|s| ids[s.first()]).collect::<Vec<_>>();
+    let parts = stitched
+        .iter()
+        .map(|s| s.parts.len() as i32)
+        .collect::<Vec<_>>();
+    let geoms = stitched
+        .iter()
+        .map(|s| geojson::Geometry::new(geojson::Value::from(&s.line)).to_string())
+        .collect::<Vec<_>>();
+
+    #[rustfmt::skip]
+    sqlx::query("DROP TABLE IF EXISTS road")
+        .execute(&pool)
+        .await
+        .unwrap();
+
+    // `rdcl` is the first fragment, carrying the attributes of the whole road and its direction
+    #[rustfmt::skip]
+    sqlx::query("CREATE TABLE IF NOT EXISTS road (id Serial PRIMARY KEY, rdcl Int4, parts Int4, geom Geometry(LineString, 6668))")
+        .execute(&pool)
+        .await
+        .unwrap();
+
+    #[rustfmt::skip]
+    sqlx::query("INSERT INTO road (rdcl, parts, geom) SELECT rdcl, parts, ST_SetSRID(ST_GeomFromGeoJSON(geom), 6668) FROM unnest($1, $2, $3) AS _(rdcl, parts, geom)")
+        .bind(&rdcls)
+        .bind(&parts)
+        .bind(&geoms)
+        .execute(&pool)
+        .await
+        .unwrap();
+}
@@ -0,0 +1,114 @@
+//! On-disk tile cache laid out as `<root>/<layer>/<z>/<x>/<y>.geojson`.
+//!
+//! Next to the body, `<y>.meta` keeps the `ETag` and `Last-Modified` headers for revalidation and
+//! `<y>.empty` marks a tile the server answered with 404.
+
+#[derive(Debug, Clone)]
+pub struct Cache {
+    root: std::path::PathBuf,
+}
+
+pub struct Slot {
+    dir: std::path::PathBuf,
+    stem: String,
+}
+
+pub struct Cached {
+    /// `None` for an empty tile
+    pub body: Option<String>,
+    pub etag: Option<String>,
+    pub last_modified: Option<String>,
+}
+
+impl Cache {
+    pub fn new(root: impl Into<std::path::PathBuf>) -> Self {
+        Self { root: root.into() }
+    }
+
+    pub fn slot(&self, layer: &crate::TileLayer, xtile: u32, ytile: u32) -> Slot {
+        Slot {
+            dir: self
+                .root
+                .join(layer.name)
+                .join(layer.zoom.to_string())
+                .join(xtile.to_string()),
+            stem: ytile.to_string(),
+        }
+    }
+}
+
+impl Slot {
+    fn path(&self, extension: &str) -> std::path::PathBuf {
+        self.dir.join(format!("{}.{}", self.stem, extension))
+    }
+
+    pub async fn load(&self) -> Option<Cached> {
+        if tokio::fs::try_exists(self.path("empty"))
+            .await
+            .unwrap_or(false)
+        {
+            return Some(Cached {
+                body: None,
+                etag: None,
+                last_modified: None,
+            });
+        }
+
+        let body = tokio::fs::read_to_string(self.path("geojson")).await.ok()?;
+        let meta = tokio::fs::read_to_string(self.path("meta"))
+            .await
+            .unwrap_or_default();
+
+        let mut cached = Cached {
+            body: Some(body),
+            etag: None,
+            last_modified: None,
+        };
+
+        for line in meta.lines() {
+            match line.split_once(' ') {
+                Some(("etag", value)) => cached.etag = Some(value.to_string()),
+                Some(("last-modified", value)) => cached.last_modified = Some(value.to_string()),
+                _ => (),
+            }
+        }
+
+        Some(cached)
+    }
+
+    pub async fn store(
+        &self,
+        body: &str,
+        etag: Option<&str>,
+        last_modified: Option<&str>,
+    ) -> std::io::Result<()> {
+        let mut meta = String::new();
+        if let Some(etag) = etag {
+            meta.push_str(&format!("etag {}\n", etag));
+        }
+        if let Some(last_modified) = last_modified {
+            meta.push_str(&format!("last-modified {}\n", last_modified));
+        }
+
+        tokio::fs::create_dir_all(&self.dir).await?;
+        self.write("geojson", body).await?;
+        self.write("meta", &meta).await?;
+
+        match tokio::fs::remove_file(self.path("empty")).await {
+            Err(err) if err.kind() != std::io::ErrorKind::NotFound => Err(err),
+            _ => Ok(()),
+        }
+    }
+
+    pub async fn store_empty(&self) -> std::io::Result<()> {
+        tokio::fs::create_dir_all(&self.dir).await?;
+        self.write("empty", "").await
+    }
+
+    /// Write through a temporary file so an interrupted run never leaves a truncated tile.
+    async fn write(&self, extension: &str, contents: &str) -> std::io::Result<()> {
+        let temp = self.path(&format!("{}.tmp", extension));
+        tokio::fs::write(&temp, contents).await?;
+        tokio::fs::rename(&temp, self.path(extension)).await
+    }
+}
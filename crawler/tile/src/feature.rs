@@ -0,0 +1,147 @@
+//! Splitting tile features into per-table batches and counting what is skipped.
+
+/// Rows for one table, one value list per column, the properties as JSON and the GeoJSON
+/// geometries.
+#[derive(Debug, Default)]
+pub struct Batch {
+    pub values: Vec<Vec<Option<String>>>,
+    pub properties: Vec<String>,
+    pub geometries: Vec<String>,
+}
+
+/// Features of a tile which were not stored.
+#[derive(Debug, Clone, Default)]
+pub struct Skipped {
+    pub no_geometry: usize,
+    /// geometry type names no table of the layer accepts
+    pub shapes: std::collections::BTreeMap<String, usize>,
+}
+
+impl Skipped {
+    pub fn count(&self) -> usize {
+        self.no_geometry + self.shapes.values().sum::<usize>()
+    }
+
+    fn merge(&mut self, other: &Skipped) {
+        self.no_geometry += other.no_geometry;
+        for (shape, count) in &other.shapes {
+            *self.shapes.entry(shape.clone()).or_default() += count;
+        }
+    }
+}
+
+/// Split the features into one batch per table of the layer, multi geometries exploded into parts.
+pub fn split(
+    layer: &crate::TileLayer,
+    features: geojson::FeatureCollection,
+) -> (Vec<Batch>, Skipped) {
+    let mut batches = layer
+        .tables
+        .iter()
+        .map(|_| Batch {
+            values: vec![vec![]; layer.columns.len()],
+            ..Default::default()
+        })
+        .collect::<Vec<_>>();
+    let mut skipped = Skipped::default();
+
+    for feature in features {
+        let geojson::Feature {
+            geometry,
+            properties,
+            ..
+        } = feature;
+
+        let geometry = match geometry {
+            Some(inner) => inner,
+            None => {
+                skipped.no_geometry += 1;
+                continue;
+            }
+        };
+
+        let routed = layer
+            .tables
+            .iter()
+            .zip(batches.iter_mut())
+            .find_map(|(table, batch)| Some((table.shape.parts(&geometry.value)?, batch)));
+
+        let (parts, batch) = match routed {
+            Some(inner) => inner,
+            None => {
+                let shape = geometry.value.type_name().to_string();
+                *skipped.shapes.entry(shape).or_default() += 1;
+                continue;
+            }
+        };
+
+        let values = layer
+            .columns
+            .iter()
+            .map(
+                |column| match properties.as_ref().and_then(|p| p.get(column.property)) {
+                    Some(geojson::JsonValue::String(inner)) => Some(inner.clone()),
+                    Some(geojson::JsonValue::Null) | None => None,
+                    Some(inner) => Some(inner.to_string()),
+                },
+            )
+            .collect::<Vec<_>>();
+
+        let attributes = match properties {
+            Some(inner) => geojson::JsonValue::Object(inner).to_string(),
+            None => "{}".to_string(),
+        };
+
+        for part in parts {
+            for (column, value) in batch.values.iter_mut().zip(values.iter()) {
+                column.push(value.clone());
+            }
+            batch.properties.push(attributes.clone());
+            batch
+                .geometries
+                .push(geojson::Geometry::new(part).to_string());
+        }
+    }
+
+    (batches, skipped)
+}
+
+/// Feature counts of a whole run.
+#[derive(Debug, Default)]
+pub struct Report {
+    stored: usize,
+    skipped: Skipped,
+    /// tiles with skipped features and their counts
+    tiles: Vec<((u32, u32), usize)>,
+}
+
+impl Report {
+    pub fn add(&mut self, xtile: u32, ytile: u32, entry: &crate::ledger::Entry, skipped: Skipped) {
+        self.stored += entry.feature_count;
+        if skipped.count() > 0 {
+            self.tiles.push(((xtile, ytile), skipped.count()));
+        }
+        self.skipped.merge(&skipped);
+    }
+
+    pub fn print(&mut self) {
+        println!(
+            "[feature stats] stored: {}, skipped: {}, tiles with skipped features: {}",
+            self.stored,
+            self.skipped.count(),
+            self.tiles.len()
+        );
+
+        if self.skipped.no_geometry > 0 {
+            println!("[feature stats] no geometry: {}", self.skipped.no_geometry);
+        }
+        for (shape, count) in &self.skipped.shapes {
+            println!("[feature stats] unsupported {}: {}", shape, count);
+        }
+
+        self.tiles.sort();
+        for ((xtile, ytile), count) in &self.tiles {
+            println!("[feature stats] ({}, {}): skipped {}", xtile, ytile, count);
+        }
+    }
+}
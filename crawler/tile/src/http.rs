@@ -0,0 +1,220 @@
+//! Polite tile download: on-disk cache, retry with exponential backoff and a request rate ceiling.
+
+use crate::cache::Cached;
+
+/// How failed requests are retried. Network errors, 429 and 5xx are retryable, 404 is an empty
+/// tile and any other status fails at once.
+#[derive(Debug, Clone, Copy)]
+pub struct RetryPolicy {
+    /// attempts per tile, including the first one
+    pub max_attempts: u32,
+    /// wait before the first retry, doubled every retry
+    pub backoff: std::time::Duration,
+    /// upper bound of the wait
+    pub max_backoff: std::time::Duration,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            max_attempts: 5,
+            backoff: std::time::Duration::from_millis(500),
+            max_backoff: std::time::Duration::from_secs(30),
+        }
+    }
+}
+
+impl RetryPolicy {
+    fn delay(&self, attempt: u32) -> std::time::Duration {
+        self.backoff
+            .saturating_mul(2u32.saturating_pow(attempt.saturating_sub(1)))
+            .min(self.max_backoff)
+    }
+}
+
+/// Spaces requests so that at most `per_second` of them start every second.
+pub struct RateLimit {
+    interval: std::time::Duration,
+    next: tokio::sync::Mutex<tokio::time::Instant>,
+}
+
+impl RateLimit {
+    /// Panics unless `per_second` is greater than zero.
+    pub fn new(per_second: f64) -> Self {
+        assert!(
+            per_second > 0.0 && per_second.is_finite(),
+            "rate limit must be greater than zero, got {}",
+            per_second
+        );
+
+        Self {
+            interval: std::time::Duration::from_secs_f64(1.0 / per_second),
+            next: tokio::sync::Mutex::new(tokio::time::Instant::now()),
+        }
+    }
+
+    pub async fn wait(&self) {
+        let at = {
+            let mut next = self.next.lock().await;
+            let at = tokio::time::Instant::now().max(*next);
+            *next = at + self.interval;
+            at
+        };
+
+        tokio::time::sleep_until(at).await;
+    }
+}
+
+pub enum Download {
+    Body(String),
+    /// 404, the tile has no features
+    Empty,
+}
+
+#[derive(Debug)]
+pub struct Failure {
+    pub http_code: Option<u16>,
+}
+
+/// Tile source combining the http client, the cache and the politeness settings.
+pub struct Downloader {
+    pub client: reqwest::Client,
+    pub retry: RetryPolicy,
+    pub limit: RateLimit,
+    pub cache: Option<crate::cache::Cache>,
+    /// serve from the cache only, missing tiles fail without a request
+    pub offline: bool,
+}
+
+impl Downloader {
+    pub async fn download(
+        &self,
+        layer: &crate::TileLayer,
+        xtile: u32,
+        ytile: u32,
+    ) -> Result<(u16, Download), Failure> {
+        let url = layer.url(xtile, ytile);
+        let slot = self
+            .cache
+            .as_ref()
+            .map(|cache| cache.slot(layer, xtile, ytile));
+
+        let cached = match &slot {
+            Some(slot) => slot.load().await,
+            None => None,
+        };
+
+        if self.offline {
+            return match cached {
+                Some(Cached {
+                    body: Some(body), ..
+                }) => Ok((200, Download::Body(body))),
+                Some(Cached { body: None, .. }) => Ok((404, Download::Empty)),
+                None => {
+                    log::warn!("{}: not cached", url);
+                    Err(Failure { http_code: None })
+                }
+            };
+        }
+
+        let mut attempt = 0;
+
+        loop {
+            attempt += 1;
+            self.limit.wait().await;
+
+            let mut request = self.client.get(&url);
+            if let Some(cached) = &cached {
+                if let Some(etag) = &cached.etag {
+                    request = request.header(reqwest::header::IF_NONE_MATCH, etag);
+                }
+                if let Some(last_modified) = &cached.last_modified {
+                    request = request.header(reqwest::header::IF_MODIFIED_SINCE, last_modified);
+                }
+            }
+
+            let (code, retry_after) = match request.send().await {
+                Ok(response) => {
+                    let status = response.status();
+
+                    if status == reqwest::StatusCode::NOT_MODIFIED {
+                        if let Some(Cached {
+                            body: Some(body), ..
+                        }) = cached
+                        {
+                            return Ok((status.as_u16(), Download::Body(body)));
+                        }
+                        log::warn!("{}: not modified but not cached", url);
+                        (Some(status.as_u16()), None)
+                    } else if status == reqwest::StatusCode::OK {
+                        let etag = header(&response, reqwest::header::ETAG);
+                        let last_modified = header(&response, reqwest::header::LAST_MODIFIED);
+
+                        match response.text().await {
+                            Ok(text) => {
+                                if let Some(slot) = &slot {
+                                    let stored = slot.store(
+                                        &text,
+                                        etag.as_deref(),
+                                        last_modified.as_deref(),
+                                    );
+                                    if let Err(err) = stored.await {
+                                        log::warn!("{}: failed to write cache ({})", url, err);
+                                    }
+                                }
+                                return Ok((status.as_u16(), Download::Body(text)));
+                            }
+                            Err(err) => {
+                                log::warn!("{}: failed to read http body ({})", url, err);
+                                (Some(status.as_u16()), None)
+                            }
+                        }
+                    } else if status == reqwest::StatusCode::NOT_FOUND {
+                        if let Some(slot) = &slot {
+                            if let Err(err) = slot.store_empty().await {
+                                log::warn!("{}: failed to write cache ({})", url, err);
+                            }
+                        }
+                        return Ok((status.as_u16(), Download::Empty));
+                    } else if status == reqwest::StatusCode::TOO_MANY_REQUESTS
+                        || status.is_server_error()
+                    {
+                        log::warn!("{}: retryable http status ({})", url, status);
+                        let retry_after = header(&response, reqwest::header::RETRY_AFTER)
+                            .and_then(|value| value.parse::<u64>().ok())
+                            .map(std::time::Duration::from_secs);
+                        (Some(status.as_u16()), retry_after)
+                    } else {
+                        log::warn!("{}: invalid http status ({})", url, status);
+                        return Err(Failure {
+                            http_code: Some(status.as_u16()),
+                        });
+                    }
+                }
+                Err(err) => {
+                    log::warn!("{}: failed to request on http ({})", url, err);
+                    (None, None)
+                }
+            };
+
+            if attempt >= self.retry.max_attempts {
+                log::warn!("{}: gave up after {} attempts", url, attempt);
+                return Err(Failure { http_code: code });
+            }
+
+            let delay = self
+                .retry
+                .delay(attempt)
+                .max(retry_after.unwrap_or_default());
+            tokio::time::sleep(delay).await;
+        }
+    }
+}
+
+fn header(response: &reqwest::Response, name: reqwest::header::HeaderName) -> Option<String> {
+    response
+        .headers()
+        .get(name)
+        .and_then(|value| value.to_str().ok())
+        .map(|value| value.to_string())
+}
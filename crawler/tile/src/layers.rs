@@ -0,0 +1,106 @@
+//! GSI experimental vector tile layers.
+//!
+//! https://maps.gsi.go.jp/development/vt_expt.html
+
+use crate::{Column, Kind, Shape, Table, TileLayer};
+
+/// Every layer, a new one only needs its entry here.
+pub const ALL: &[TileLayer] = &[RDCL, FGD];
+
+/// The layer of the given name, for the `--layer` flag.
+pub fn find(name: &str) -> Result<TileLayer, String> {
+    match ALL.iter().find(|layer| layer.name == name) {
+        Some(inner) => Ok(*inner),
+        None => {
+            let names = ALL.iter().map(|layer| layer.name).collect::<Vec<_>>();
+            Err(format!(
+                "unknown layer {}, expected {}",
+                name,
+                names.join(" or ")
+            ))
+        }
+    }
+}
+
+/// Road centre lines (道路中心線).
+///
+/// The road class (`rdCtg`), the width category (`rnkWidth`) and the rest of the attributes are
+/// kept for the graph builder.
+pub const RDCL: TileLayer = TileLayer {
+    name: "rdcl",
+    url: "https://cyberjapandata.gsi.go.jp/xyz/experimental_rdcl/{z}/{x}/{y}.geojson",
+    zoom: 16,
+    tables: &[Table {
+        shape: Shape::LineString,
+        name: "rdcl",
+    }],
+    columns: &[
+        Column {
+            property: "rID",
+            name: "rid",
+            kind: Kind::Text,
+        },
+        Column {
+            property: "type",
+            name: "type",
+            kind: Kind::Text,
+        },
+        Column {
+            property: "rdCtg",
+            name: "rdctg",
+            kind: Kind::Text,
+        },
+        Column {
+            property: "rnkWidth",
+            name: "rnkwidth",
+            kind: Kind::Text,
+        },
+        Column {
+            property: "lvOrder",
+            name: "lvorder",
+            kind: Kind::Int4,
+        },
+    ],
+    properties: true,
+};
+
+/// Fundamental geospatial data (基盤地図情報), road edges and others, with areas and points kept in
+/// their own tables.
+pub const FGD: TileLayer = TileLayer {
+    name: "fgd",
+    url: "https://cyberjapandata.gsi.go.jp/xyz/experimental_fgd/{z}/{x}/{y}.geojson",
+    zoom: 18,
+    tables: &[
+        Table {
+            shape: Shape::LineString,
+            name: "fgd",
+        },
+        Table {
+            shape: Shape::Polygon,
+            name: "fgd_polygon",
+        },
+        Table {
+            shape: Shape::Point,
+            name: "fgd_point",
+        },
+    ],
+    columns: &[Column {
+        property: "type",
+        name: "type",
+        kind: Kind::Text,
+    }],
+    properties: false,
+};
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn finds_every_layer_by_its_name() {
+        for layer in ALL {
+            assert_eq!(find(layer.name).unwrap().url, layer.url);
+        }
+        assert!(find("road").is_err());
+    }
+}
@@ -0,0 +1,122 @@
+//! Per tile crawl status, kept across runs so a rerun only fetches failed or missing tiles.
+
+const CREATE_SQL: &str = "CREATE TABLE IF NOT EXISTS ledger (layer Text, z Int4, x Int4, y Int4, status Text, http_code Int4, feature_count Int4, updated_at Timestamptz, PRIMARY KEY (layer, z, x, y))";
+
+// ledgers created before skipped features were counted
+const ALTER_SQL: &str = "ALTER TABLE ledger ADD COLUMN IF NOT EXISTS skipped_count Int4";
+
+const RECORD_SQL: &str = "INSERT INTO ledger (layer, z, x, y, status, http_code, feature_count, skipped_count, updated_at) VALUES ($1, $2, $3, $4, $5, $6, $7, $8, now()) ON CONFLICT (layer, z, x, y) DO UPDATE SET status = excluded.status, http_code = excluded.http_code, feature_count = excluded.feature_count, skipped_count = excluded.skipped_count, updated_at = excluded.updated_at";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Status {
+    Done,
+    /// the server has no such tile
+    Empty,
+    Failed,
+}
+
+impl Status {
+    fn as_str(&self) -> &'static str {
+        match self {
+            Status::Done => "done",
+            Status::Empty => "empty",
+            Status::Failed => "failed",
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Entry {
+    pub status: Status,
+    pub http_code: Option<u16>,
+    pub feature_count: usize,
+    pub skipped_count: usize,
+}
+
+impl Entry {
+    pub fn done(http_code: u16, feature_count: usize, skipped_count: usize) -> Self {
+        Self {
+            status: Status::Done,
+            http_code: Some(http_code),
+            feature_count,
+            skipped_count,
+        }
+    }
+
+    pub fn empty(http_code: u16) -> Self {
+        Self {
+            status: Status::Empty,
+            http_code: Some(http_code),
+            feature_count: 0,
+            skipped_count: 0,
+        }
+    }
+
+    pub fn failed(http_code: Option<u16>) -> Self {
+        Self {
+            status: Status::Failed,
+            http_code,
+            feature_count: 0,
+            skipped_count: 0,
+        }
+    }
+}
+
+pub async fn create(pool: &sqlx::PgPool) -> Result<(), sqlx::Error> {
+    sqlx::query(CREATE_SQL).execute(pool).await?;
+    sqlx::query(ALTER_SQL).execute(pool).await?;
+    Ok(())
+}
+
+pub async fn clear(pool: &sqlx::PgPool, layer: &crate::TileLayer) -> Result<(), sqlx::Error> {
+    #[rustfmt::skip]
+    sqlx::query("DELETE FROM ledger WHERE layer = $1 AND z = $2")
+        .bind(layer.name)
+        .bind(layer.zoom as i32)
+        .execute(pool)
+        .await?;
+    Ok(())
+}
+
+/// Tiles of the layer already stored or known to be empty.
+pub async fn done(
+    pool: &sqlx::PgPool,
+    layer: &crate::TileLayer,
+) -> Result<std::collections::HashSet<(u32, u32)>, sqlx::Error> {
+    #[rustfmt::skip]
+    let tiles: Vec<(i32, i32)> = sqlx::query_as("SELECT x, y FROM ledger WHERE layer = $1 AND z = $2 AND status <> $3")
+        .bind(layer.name)
+        .bind(layer.zoom as i32)
+        .bind(Status::Failed.as_str())
+        .fetch_all(pool)
+        .await?;
+
+    Ok(tiles
+        .into_iter()
+        .map(|(x, y)| (x as u32, y as u32))
+        .collect())
+}
+
+pub async fn record<'e, E>(
+    executor: E,
+    layer: &crate::TileLayer,
+    xtile: u32,
+    ytile: u32,
+    entry: &Entry,
+) -> Result<(), sqlx::Error>
+where
+    E: sqlx::PgExecutor<'e>,
+{
+    sqlx::query(RECORD_SQL)
+        .bind(layer.name)
+        .bind(layer.zoom as i32)
+        .bind(xtile as i32)
+        .bind(ytile as i32)
+        .bind(entry.status.as_str())
+        .bind(entry.http_code.map(|code| code as i32))
+        .bind(entry.feature_count as i32)
+        .bind(entry.skipped_count as i32)
+        .execute(executor)
+        .await?;
+    Ok(())
+}
@@ -0,0 +1,603 @@
+use futures::StreamExt;
+
+mod cache;
+mod feature;
+mod http;
+pub mod layers;
+mod ledger;
+pub mod stitch;
+
+pub use http::RetryPolicy;
+
+/// Description of a GSI xyz vector tile layer and the tables its features go to.
+#[derive(Debug, Clone, Copy)]
+pub struct TileLayer {
+    /// layer name, the key in the ledger, the cache and the zoom config
+    pub name: &'static str,
+    /// url template, `{z}`, `{x}` and `{y}` are replaced by the tile coordinate
+    pub url: &'static str,
+    pub zoom: u8,
+    /// destination table per geometry shape, features of other shapes are skipped
+    pub tables: &'static [Table],
+    /// feature properties stored as typed columns
+    pub columns: &'static [Column],
+    /// also keep every feature property in a Jsonb `properties` column
+    pub properties: bool,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct Table {
+    pub shape: Shape,
+    pub name: &'static str,
+}
+
+/// Geometry shape of a table, multi geometries are stored as one row per part.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Shape {
+    Point,
+    LineString,
+    Polygon,
+}
+
+impl Shape {
+    fn sql(&self) -> &'static str {
+        match self {
+            Shape::Point => "Point",
+            Shape::LineString => "LineString",
+            Shape::Polygon => "Polygon",
+        }
+    }
+
+    /// Parts of the geometry if it has this shape.
+    fn parts(&self, value: &geojson::Value) -> Option<Vec<geojson::Value>> {
+        use geojson::Value;
+
+        match (self, value) {
+            (Shape::Point, Value::Point(_))
+            | (Shape::LineString, Value::LineString(_))
+            | (Shape::Polygon, Value::Polygon(_)) => Some(vec![value.clone()]),
+            (Shape::Point, Value::MultiPoint(parts)) => {
+                Some(parts.iter().cloned().map(Value::Point).collect())
+            }
+            (Shape::LineString, Value::MultiLineString(parts)) => {
+                Some(parts.iter().cloned().map(Value::LineString).collect())
+            }
+            (Shape::Polygon, Value::MultiPolygon(parts)) => {
+                Some(parts.iter().cloned().map(Value::Polygon).collect())
+            }
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct Column {
+    /// property key in the feature
+    pub property: &'static str,
+    /// column name in the table
+    pub name: &'static str,
+    pub kind: Kind,
+}
+
+/// Postgresql type of a property column, values which are missing or empty become NULL.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Kind {
+    Text,
+    Int4,
+    Float8,
+}
+
+impl Kind {
+    fn sql(&self) -> &'static str {
+        match self {
+            Kind::Text => "Text",
+            Kind::Int4 => "Int4",
+            Kind::Float8 => "Float8",
+        }
+    }
+}
+
+impl TileLayer {
+    pub fn url(&self, xtile: u32, ytile: u32) -> String {
+        self.url
+            .replace("{z}", &self.zoom.to_string())
+            .replace("{x}", &xtile.to_string())
+            .replace("{y}", &ytile.to_string())
+    }
+
+    fn create_sql(&self, table: &Table) -> String {
+        let mut columns = vec!["id Serial PRIMARY KEY".to_string()];
+        for column in self.columns {
+            columns.push(format!("{} {}", column.name, column.kind.sql()));
+        }
+        if self.properties {
+            columns.push("properties Jsonb".to_string());
+        }
+        columns.push(format!("geom Geometry({}, 6668)", table.shape.sql()));
+
+        format!(
+            "CREATE TABLE IF NOT EXISTS {} ({})",
+            table.name,
+            columns.join(", ")
+        )
+    }
+
+    /// Property columns of the tables, which a table from an older crawl may lack.
+    fn column_names(&self) -> Vec<&'static str> {
+        let mut names = self.columns.iter().map(|c| c.name).collect::<Vec<_>>();
+        if self.properties {
+            names.push("properties");
+        }
+        names
+    }
+
+    /// Insert statement taking one text array per column, the properties and the geometries.
+    fn insert_sql(&self, table: &Table) -> String {
+        let mut names = vec![];
+        let mut values = vec![];
+
+        for column in self.columns {
+            names.push(column.name.to_string());
+            values.push(match column.kind {
+                Kind::Text => column.name.to_string(),
+                kind => format!("NULLIF({}, '')::{}", column.name, kind.sql()),
+            });
+        }
+        if self.properties {
+            names.push("properties".to_string());
+            values.push("properties::Jsonb".to_string());
+        }
+        names.push("geom".to_string());
+        values.push("ST_SetSRID(ST_GeomFromGeoJSON(geom), 6668)".to_string());
+
+        let params = (1..=names.len())
+            .map(|i| format!("${}", i))
+            .collect::<Vec<_>>();
+
+        format!(
+            "INSERT INTO {} ({}) SELECT {} FROM unnest({}) AS _({})",
+            table.name,
+            names.join(", "),
+            values.join(", "),
+            params.join(", "),
+            names.join(", "),
+        )
+    }
+}
+
+/// Crawl behaviour shared by every layer.
+#[derive(Debug, Clone)]
+pub struct Options {
+    /// clear the table and the ledger instead of resuming
+    pub fresh: bool,
+    pub retry: RetryPolicy,
+    /// ceiling of requests started per second
+    pub rate_limit: f64,
+    /// tiles in flight at once
+    pub concurrency: usize,
+    /// directory of the on-disk tile cache, `None` disables caching
+    pub cache: Option<std::path::PathBuf>,
+    /// read tiles from the cache only
+    pub offline: bool,
+}
+
+impl Default for Options {
+    fn default() -> Self {
+        Self {
+            fresh: false,
+            retry: RetryPolicy::default(),
+            rate_limit: 50.0,
+            concurrency: 64,
+            cache: Some("cache".into()),
+            offline: false,
+        }
+    }
+}
+
+/// Crawl flags, unset ones fall back to [`Options::default`].
+#[derive(Debug, Clone, clap::Args)]
+#[group(id = "crawl")]
+pub struct Args {
+    /// tile zoom level, overriding the config file
+    #[arg(long)]
+    pub zoom: Option<u8>,
+    /// clear the table and the ledger instead of resuming
+    #[arg(long)]
+    pub fresh: bool,
+    /// read tiles from the cache only
+    #[arg(long)]
+    pub offline: bool,
+    /// directory of the on-disk tile cache
+    #[arg(long)]
+    pub cache: Option<std::path::PathBuf>,
+    /// disable the on-disk tile cache
+    #[arg(long, conflicts_with_all = ["cache", "offline"])]
+    pub no_cache: bool,
+    /// ceiling of requests started per second
+    #[arg(long, value_parser = area::parse::positive)]
+    pub rate_limit: Option<f64>,
+    /// tiles in flight at once
+    #[arg(long, value_parser = area::parse::count)]
+    pub concurrency: Option<usize>,
+    /// attempts per tile, including the first one
+    #[arg(long)]
+    pub max_attempts: Option<u32>,
+}
+
+impl Args {
+    /// The layer at the zoom level of the flags, the config file or its default, in that order.
+    pub fn layer(&self, layer: TileLayer, config: &area::Config) -> TileLayer {
+        let zoom = self
+            .zoom
+            .or(config.zoom.get(layer.name).copied())
+            .unwrap_or(layer.zoom);
+
+        TileLayer { zoom, ..layer }
+    }
+
+    pub fn options(&self) -> Options {
+        let default = Options::default();
+
+        Options {
+            fresh: self.fresh,
+            retry: RetryPolicy {
+                max_attempts: self.max_attempts.unwrap_or(default.retry.max_attempts),
+                ..default.retry
+            },
+            rate_limit: self.rate_limit.unwrap_or(default.rate_limit),
+            concurrency: self.concurrency.unwrap_or(default.concurrency),
+            cache: match self.no_cache {
+                true => None,
+                false => self.cache.clone().or(default.cache),
+            },
+            offline: self.offline,
+        }
+    }
+}
+
+/// Crawl the layer over the study area of the flags, logging to `crawler.log`.
+pub async fn run(layer: TileLayer, area: &area::Args, args: &Args) {
+    match simple_logging::log_to_file("crawler.log", log::LevelFilter::Info) {
+        Ok(_) => (),
+        Err(err) => {
+            log::error!("failed to open log file ({})", err);
+            panic!("failed to open log file ({})", err);
+        }
+    }
+
+    let config = area.config();
+
+    let layer = args.layer(layer, &config);
+    let area = config.area.load().await;
+    let tiles = tiles(&area, layer.zoom);
+
+    crawl(&layer, tiles, &args.options()).await;
+}
+
+/// Tiles intersecting the area at the zoom level, tiles on the boundary included.
+pub fn tiles(area: &geo::MultiPolygon<f64>, zoom: u8) -> Vec<(u32, u32)> {
+    let rect = match geo::BoundingRect::bounding_rect(area) {
+        Some(inner) => inner,
+        None => return vec![],
+    };
+
+    let (x0, y0) = tile_of(rect.min().x, rect.max().y, zoom);
+    let (x1, y1) = tile_of(rect.max().x, rect.min().y, zoom);
+
+    let mut tiles = vec![];
+    for y in y0..=y1 {
+        for x in x0..=x1 {
+            if geo::Intersects::intersects(area, &tile_rect(x, y, zoom)) {
+                tiles.push((x, y));
+            }
+        }
+    }
+
+    tiles
+}
+
+/// Web mercator tile containing the point.
+fn tile_of(lon: f64, lat: f64, zoom: u8) -> (u32, u32) {
+    let n = (1u64 << zoom) as f64;
+    let x = (lon + 180.0) / 360.0 * n;
+    let y = (1.0 - lat.to_radians().tan().asinh() / std::f64::consts::PI) / 2.0 * n;
+    let clamp = |v: f64| v.floor().clamp(0.0, n - 1.0) as u32;
+    (clamp(x), clamp(y))
+}
+
+/// Longitude and latitude extent of the tile.
+fn tile_rect(x: u32, y: u32, zoom: u8) -> geo::Rect<f64> {
+    let n = (1u64 << zoom) as f64;
+    let lon = |x: u32| x as f64 / n * 360.0 - 180.0;
+    let lat = |y: u32| {
+        (std::f64::consts::PI * (1.0 - 2.0 * y as f64 / n))
+            .sinh()
+            .atan()
+            .to_degrees()
+    };
+    geo::Rect::new(
+        geo::coord! { x: lon(x), y: lat(y + 1) },
+        geo::coord! { x: lon(x + 1), y: lat(y) },
+    )
+}
+
+/// Create the layer tables if needed and fill them with the features of every tile which is not
+/// yet recorded as done in the ledger.
+pub async fn crawl(layer: &TileLayer, tiles: Vec<(u32, u32)>, options: &Options) {
+    #[rustfmt::skip]
+    let pool = match sqlx::postgres::PgPoolOptions::new()
+        .connect("postgres://postgres:0@localhost/postgres")
+        .await
+    {
+        Ok(inner) => inner,
+        Err(err) => {
+            log::error!("failed to connect postgresql ({})", err);
+            panic!("failed to connect postgresql ({})", err);
+        }
+    };
+    let pool = std::sync::Arc::new(pool);
+
+    for table in layer.tables {
+        if options.fresh {
+            #[rustfmt::skip]
+            match sqlx::query(&format!("DROP TABLE IF EXISTS {}", table.name))
+                .execute(&*pool)
+                .await
+            {
+                Ok(_) => (),
+                Err(err) => {
+                    log::error!("failed to drop table ({})", err);
+                    panic!("failed to drop table ({})", err);
+                }
+            };
+        }
+
+        // adding the missing columns would leave the tiles already done without them
+        #[rustfmt::skip]
+        let existing: Vec<(String,)> = match sqlx::query_as("SELECT column_name::Text FROM information_schema.columns WHERE table_schema = current_schema() AND table_name = $1")
+            .bind(table.name)
+            .fetch_all(&*pool)
+            .await
+        {
+            Ok(inner) => inner,
+            Err(err) => {
+                log::error!("failed to read table columns ({})", err);
+                panic!("failed to read table columns ({})", err);
+            }
+        };
+        let missing = layer
+            .column_names()
+            .into_iter()
+            .filter(|name| !existing.is_empty() && !existing.iter().any(|(e,)| e == name))
+            .collect::<Vec<_>>();
+        if !missing.is_empty() {
+            let message = format!(
+                "table {} was created by an older crawl without {}, rerun with --fresh",
+                table.name,
+                missing.join(", ")
+            );
+            log::error!("{}", message);
+            panic!("{}", message);
+        }
+
+        #[rustfmt::skip]
+        match sqlx::query(&layer.create_sql(table))
+            .execute(&*pool)
+            .await
+        {
+            Ok(_) => (),
+            Err(err) => {
+                log::error!("failed to create table ({})", err);
+                panic!("failed to create table ({})", err);
+            }
+        };
+    }
+
+    if let Err(err) = ledger::create(&pool).await {
+        log::error!("failed to create ledger ({})", err);
+        panic!("failed to create ledger ({})", err);
+    }
+
+    if options.fresh {
+        if let Err(err) = ledger::clear(&pool, layer).await {
+            log::error!("failed to clear ledger ({})", err);
+            panic!("failed to clear ledger ({})", err);
+        }
+    }
+
+    let done = match ledger::done(&pool, layer).await {
+        Ok(inner) => inner,
+        Err(err) => {
+            log::error!("failed to read ledger ({})", err);
+            panic!("failed to read ledger ({})", err);
+        }
+    };
+
+    let total = tiles.len();
+    let tiles = tiles
+        .into_iter()
+        .filter(|tile| !done.contains(tile))
+        .collect::<Vec<_>>();
+
+    println!(
+        "[ledger stats] tiles: {}, done: {}, remaining: {}",
+        total,
+        total - tiles.len(),
+        tiles.len()
+    );
+
+    let insert_sqls = std::sync::Arc::new(
+        layer
+            .tables
+            .iter()
+            .map(|table| layer.insert_sql(table))
+            .collect::<Vec<_>>(),
+    );
+    let report = std::sync::Arc::new(std::sync::Mutex::new(feature::Report::default()));
+    let downloader = std::sync::Arc::new(http::Downloader {
+        client: reqwest::Client::new(),
+        retry: options.retry,
+        limit: http::RateLimit::new(options.rate_limit),
+        cache: options.cache.as_ref().map(cache::Cache::new),
+        offline: options.offline,
+    });
+    let pb = std::sync::Arc::new(indicatif::ProgressBar::new(tiles.len() as u64));
+
+    futures::stream::iter(tiles)
+        .map(|(xtile, ytile)| {
+            let pool = pool.clone();
+            let downloader = downloader.clone();
+            let pb = pb.clone();
+            let insert_sqls = insert_sqls.clone();
+            let report = report.clone();
+
+            async move {
+                pb.inc(1);
+
+                let fetched = fetch(layer, &downloader, xtile, ytile).await;
+                let entry = match fetched {
+                    Ok((entry, batches, skipped)) => {
+                        let stored =
+                            store(&pool, layer, &insert_sqls, xtile, ytile, &entry, batches);
+                        match stored.await {
+                            Ok(_) => {
+                                report.lock().unwrap().add(xtile, ytile, &entry, skipped);
+                                return;
+                            }
+                            Err(err) => {
+                                log::warn!("({}, {}): failed to insert ({})", xtile, ytile, err);
+                                ledger::Entry::failed(entry.http_code)
+                            }
+                        }
+                    }
+                    Err(entry) => entry,
+                };
+
+                if let Err(err) = ledger::record(&*pool, layer, xtile, ytile, &entry).await {
+                    log::warn!("({}, {}): failed to record ledger ({})", xtile, ytile, err);
+                }
+            }
+        })
+        .buffer_unordered(options.concurrency)
+        .collect::<Vec<_>>()
+        .await;
+
+    pb.finish();
+
+    report.lock().unwrap().print();
+}
+
+/// Download and parse a tile, failures are returned as the ledger entry to record.
+async fn fetch(
+    layer: &TileLayer,
+    downloader: &http::Downloader,
+    xtile: u32,
+    ytile: u32,
+) -> Result<(ledger::Entry, Vec<feature::Batch>, feature::Skipped), ledger::Entry> {
+    let (code, text) = match downloader.download(layer, xtile, ytile).await {
+        Ok((code, http::Download::Body(text))) => (code, text),
+        Ok((code, http::Download::Empty)) => {
+            return Ok((ledger::Entry::empty(code), vec![], Default::default()));
+        }
+        Err(failure) => return Err(ledger::Entry::failed(failure.http_code)),
+    };
+
+    let geojson = match text.parse::<geojson::GeoJson>() {
+        Ok(inner) => inner,
+        Err(err) => {
+            log::warn!(
+                "({}, {}): failed to parse as geojson ({})",
+                xtile,
+                ytile,
+                err
+            );
+            return Err(ledger::Entry::failed(Some(code)));
+        }
+    };
+
+    let features = match geojson::FeatureCollection::try_from(geojson) {
+        Ok(inner) => inner,
+        Err(err) => {
+            log::warn!(
+                "({}, {}): failed to get feature collection ({})",
+                xtile,
+                ytile,
+                err
+            );
+            return Err(ledger::Entry::failed(Some(code)));
+        }
+    };
+
+    let (batches, skipped) = feature::split(layer, features);
+    let count = batches.iter().map(|batch| batch.geometries.len()).sum();
+    let entry = ledger::Entry::done(code, count, skipped.count());
+
+    Ok((entry, batches, skipped))
+}
+
+/// Insert the features and mark the tile as done in one transaction, so a tile is never stored
+/// twice across reruns.
+async fn store(
+    pool: &sqlx::PgPool,
+    layer: &TileLayer,
+    insert_sqls: &[String],
+    xtile: u32,
+    ytile: u32,
+    entry: &ledger::Entry,
+    batches: Vec<feature::Batch>,
+) -> Result<(), sqlx::Error> {
+    let mut tx = pool.begin().await?;
+
+    for (insert_sql, batch) in insert_sqls.iter().zip(batches) {
+        if batch.geometries.is_empty() {
+            continue;
+        }
+
+        let mut query = sqlx::query(insert_sql);
+        for values in batch.values {
+            query = query.bind(values);
+        }
+        if layer.properties {
+            query = query.bind(batch.properties);
+        }
+        query.bind(batch.geometries).execute(&mut *tx).await?;
+    }
+
+    ledger::record(&mut *tx, layer, xtile, ytile, entry).await?;
+
+    tx.commit().await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn covers_the_last_row_and_column_and_skips_tiles_outside_the_area() {
+        let zoom = 4;
+        let rect = |x, y| tile_rect(x, y, zoom);
+        // west edge of the column x and north edge of the row y, and a margin
+        let w = |x| rect(x, 0).min().x;
+        let h = |y| rect(0, y).max().y;
+        let e = 1e-6;
+
+        // an L over the column x = 5 and the row y = 7 of the tiles 5 to 7, just inside them
+        let area = geo::MultiPolygon::new(vec![geo::Polygon::new(
+            geo::LineString::from(vec![
+                (w(5) + e, h(5) - e),
+                (w(6) - e, h(5) - e),
+                (w(6) - e, h(7) - e),
+                (w(8) - e, h(7) - e),
+                (w(8) - e, h(8) + e),
+                (w(5) + e, h(8) + e),
+                (w(5) + e, h(5) - e),
+            ]),
+            vec![],
+        )]);
+
+        assert_eq!(
+            tiles(&area, zoom),
+            vec![(5, 5), (5, 6), (5, 7), (6, 7), (7, 7)]
+        );
+    }
+}
@@ -0,0 +1,339 @@
+//! Stitching of line features split at tile boundaries.
+//!
+//! A road crossing a tile edge is delivered as one fragment per tile, and features lying on a
+//! boundary may be delivered by both tiles. Exact duplicates are dropped, then fragments with the
+//! same key meeting end to end are merged as long as no other line touches the shared point and
+//! the direction barely changes there.
+
+#[derive(Debug, Clone, Copy)]
+pub struct Options {
+    /// endpoint snapping grid in degrees
+    pub snap: f64,
+    /// largest heading change in degrees at a merged point
+    pub max_angle: f64,
+}
+
+impl Default for Options {
+    fn default() -> Self {
+        Self {
+            snap: 1e-7,
+            max_angle: 10.0,
+        }
+    }
+}
+
+/// A stitched line and the indices of the input lines it is made of, in order along the line.
+///
+/// The line runs in the direction of its first input line, see [`Stitched::first`].
+#[derive(Debug, Clone)]
+pub struct Stitched {
+    pub line: geo::LineString<f64>,
+    pub parts: Vec<usize>,
+}
+
+impl Stitched {
+    /// The input line coming first in the input, whose direction the line keeps.
+    pub fn first(&self) -> usize {
+        *self.parts.iter().min().unwrap()
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Stats {
+    pub input: usize,
+    pub duplicates: usize,
+    pub merges: usize,
+}
+
+type Point = (i64, i64);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+enum End {
+    Start,
+    End,
+}
+
+/// Panics unless `snap` is greater than zero.
+pub fn stitch<K: Eq>(
+    lines: &[(geo::LineString<f64>, K)],
+    options: &Options,
+) -> (Vec<Stitched>, Stats) {
+    assert!(options.snap > 0.0, "snap must be greater than zero");
+
+    let quantize = |c: &geo::Coord<f64>| -> Point {
+        (
+            (c.x / options.snap).round() as i64,
+            (c.y / options.snap).round() as i64,
+        )
+    };
+
+    let mut stats = Stats {
+        input: lines.len(),
+        ..Default::default()
+    };
+
+    // exact duplicates, in either direction
+    let mut seen = std::collections::HashSet::new();
+    let mut kept = vec![];
+    for (i, (line, _)) in lines.iter().enumerate() {
+        if line.0.len() < 2 {
+            continue;
+        }
+
+        let forward = line.0.iter().map(quantize).collect::<Vec<_>>();
+        let mut backward = forward.clone();
+        backward.reverse();
+
+        if seen.contains(&forward) || seen.contains(&backward) {
+            stats.duplicates += 1;
+            continue;
+        }
+
+        seen.insert(forward);
+        kept.push(i);
+    }
+
+    // line ends meeting at every point
+    let mut ends = std::collections::HashMap::<Point, Vec<(usize, End)>>::new();
+    for &i in &kept {
+        let line = &lines[i].0;
+        ends.entry(quantize(&line.0[0]))
+            .or_default()
+            .push((i, End::Start));
+        ends.entry(quantize(&line.0[line.0.len() - 1]))
+            .or_default()
+            .push((i, End::End));
+    }
+
+    // joins, the other line end at the start and at the end of a line
+    let mut joins = std::collections::HashMap::<(usize, End), (usize, End)>::new();
+    for pair in ends.values() {
+        let [a, b] = pair.as_slice() else {
+            continue;
+        };
+
+        if a.0 == b.0 || lines[a.0].1 != lines[b.0].1 {
+            continue;
+        }
+
+        if turn(&lines[a.0].0, a.1, &lines[b.0].0, b.1) > options.max_angle {
+            continue;
+        }
+
+        joins.insert(*a, *b);
+        joins.insert(*b, *a);
+    }
+
+    let mut visited = vec![false; lines.len()];
+    let mut stitched = vec![];
+
+    for &i in &kept {
+        if visited[i] {
+            continue;
+        }
+
+        // walk back to the first line of the chain, a closed chain starts anywhere
+        let (mut first, mut entry) = (i, End::Start);
+        while let Some(&(prev, prev_end)) = joins.get(&(first, entry)) {
+            if prev == i {
+                break;
+            }
+            first = prev;
+            entry = flip(prev_end);
+        }
+
+        let mut coords: Vec<geo::Coord<f64>> = vec![];
+        let mut parts = vec![];
+        let mut reversed = false;
+        let (mut current, mut from) = (first, entry);
+
+        loop {
+            visited[current] = true;
+            parts.push(current);
+            if current == i {
+                reversed = from == End::End;
+            }
+
+            let mut part = lines[current].0 .0.clone();
+            if from == End::End {
+                part.reverse();
+            }
+            if !coords.is_empty() {
+                part.remove(0);
+            }
+            coords.extend(part);
+
+            match joins.get(&(current, flip(from))) {
+                Some(&(next, next_end)) if !visited[next] => {
+                    current = next;
+                    from = next_end;
+                }
+                _ => break,
+            }
+        }
+
+        // keep the digitising direction of the first line, as one-way roads depend on it, which is
+        // `i` as every line before it is visited
+        if reversed {
+            coords.reverse();
+            parts.reverse();
+        }
+
+        stitched.push(Stitched {
+            line: geo::LineString::new(coords),
+            parts,
+        });
+    }
+
+    stats.merges = kept.len() - stitched.len();
+
+    (stitched, stats)
+}
+
+fn flip(end: End) -> End {
+    match end {
+        End::Start => End::End,
+        End::End => End::Start,
+    }
+}
+
+/// Heading change in degrees when travelling along `a` into its `a_end` and on through `b` from its
+/// `b_end`.
+fn turn(a: &geo::LineString<f64>, a_end: End, b: &geo::LineString<f64>, b_end: End) -> f64 {
+    let n = a.0.len();
+    let (a0, a1) = match a_end {
+        End::End => (a.0[n - 2], a.0[n - 1]),
+        End::Start => (a.0[1], a.0[0]),
+    };
+
+    let m = b.0.len();
+    let (b0, b1) = match b_end {
+        End::Start => (b.0[0], b.0[1]),
+        End::End => (b.0[m - 1], b.0[m - 2]),
+    };
+
+    let h0 = (a1.y - a0.y).atan2(a1.x - a0.x);
+    let h1 = (b1.y - b0.y).atan2(b1.x - b0.x);
+    let d = (h1 - h0).to_degrees().rem_euclid(360.0);
+    d.min(360.0 - d)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn line(coords: &[(f64, f64)]) -> (geo::LineString<f64>, u8) {
+        (coords.to_vec().into(), 0)
+    }
+
+    fn coords(stitched: &Stitched) -> Vec<(f64, f64)> {
+        stitched.line.0.iter().map(|c| c.x_y()).collect()
+    }
+
+    #[test]
+    fn drops_duplicates_in_reverse() {
+        let lines = [
+            line(&[(0.0, 0.0), (1.0, 0.0), (2.0, 1.0)]),
+            line(&[(2.0, 1.0), (1.0, 0.0), (0.0, 0.0)]),
+        ];
+        let (stitched, stats) = stitch(&lines, &Options::default());
+        assert_eq!(stats.duplicates, 1);
+        assert_eq!(stitched.len(), 1);
+        assert_eq!(stitched[0].parts, vec![0]);
+    }
+
+    #[test]
+    fn merges_fragments_at_a_seam() {
+        let lines = [
+            line(&[(0.0, 0.0), (1.0, 0.0)]),
+            line(&[(1.0, 0.0), (2.0, 0.0)]),
+        ];
+        let (stitched, stats) = stitch(&lines, &Options::default());
+        assert_eq!(stats.merges, 1);
+        assert_eq!(stitched.len(), 1);
+        assert_eq!(stitched[0].parts, vec![0, 1]);
+        assert_eq!(
+            coords(&stitched[0]),
+            vec![(0.0, 0.0), (1.0, 0.0), (2.0, 0.0)]
+        );
+    }
+
+    #[test]
+    fn keeps_fragments_of_different_roads_apart() {
+        let lines = [
+            ((vec![(0.0, 0.0), (1.0, 0.0)]).into(), 0),
+            ((vec![(1.0, 0.0), (2.0, 0.0)]).into(), 1),
+        ];
+        let (stitched, _) = stitch::<u8>(&lines, &Options::default());
+        assert_eq!(stitched.len(), 2);
+    }
+
+    #[test]
+    fn does_not_merge_where_three_ends_meet() {
+        let lines = [
+            line(&[(0.0, 0.0), (1.0, 0.0)]),
+            line(&[(1.0, 0.0), (2.0, 0.0)]),
+            line(&[(1.0, 0.0), (1.0, 1.0)]),
+        ];
+        let (stitched, stats) = stitch(&lines, &Options::default());
+        assert_eq!(stats.merges, 0);
+        assert_eq!(stitched.len(), 3);
+    }
+
+    #[test]
+    fn does_not_merge_past_the_largest_angle() {
+        let lines = [
+            line(&[(0.0, 0.0), (1.0, 0.0)]),
+            line(&[(1.0, 0.0), (1.0, 1.0)]),
+        ];
+        let (stitched, _) = stitch(&lines, &Options::default());
+        assert_eq!(stitched.len(), 2);
+
+        let options = Options {
+            max_angle: 90.0,
+            ..Default::default()
+        };
+        let (stitched, _) = stitch(&lines, &options);
+        assert_eq!(stitched.len(), 1);
+    }
+
+    #[test]
+    fn keeps_the_direction_of_the_first_fragment() {
+        // the first fragment runs east, the second one was digitised west from the shared point
+        let lines = [
+            line(&[(1.0, 0.0), (2.0, 0.0)]),
+            line(&[(1.0, 0.0), (0.0, 0.0)]),
+        ];
+        let (stitched, _) = stitch(&lines, &Options::default());
+        assert_eq!(stitched.len(), 1);
+        assert_eq!(stitched[0].first(), 0);
+        assert_eq!(stitched[0].parts, vec![1, 0]);
+        assert_eq!(
+            coords(&stitched[0]),
+            vec![(0.0, 0.0), (1.0, 0.0), (2.0, 0.0)]
+        );
+
+        // and the same when the walk back enters the chain head at its end
+        let lines = [
+            line(&[(2.0, 0.0), (1.0, 0.0)]),
+            line(&[(0.0, 0.0), (1.0, 0.0)]),
+        ];
+        let (stitched, _) = stitch(&lines, &Options::default());
+        assert_eq!(stitched[0].parts, vec![0, 1]);
+        assert_eq!(
+            coords(&stitched[0]),
+            vec![(2.0, 0.0), (1.0, 0.0), (0.0, 0.0)]
+        );
+    }
+
+    #[test]
+    #[should_panic]
+    fn rejects_a_zero_snap() {
+        let lines = [line(&[(0.0, 0.0), (1.0, 0.0)])];
+        let options = Options {
+            snap: 0.0,
+            ..Default::default()
+        };
+        stitch(&lines, &options);
+    }
+}
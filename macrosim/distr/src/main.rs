@@ -62,7 +62,7 @@ async fn main() {
     let vs = pairs.iter().map(|p| p.3).collect::<Vec<_>>();
 
     #[rustfmt::skip]
-    sqlx::query("DROP TABLE IF EXISTS pair")
+    sqlx::query("DROP TABLE IF EXISTS pair CASCADE")
         .execute(&pool)
         .await
         .unwrap();
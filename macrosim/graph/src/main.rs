@@ -1,7 +1,126 @@
-const THREAD_COUNT: usize = 8;
+use clap::Parser;
+use rayon::prelude::*;
+
+/// `(id, n1, n2, distance, rdcl, width, samples, rays, rdctg, rnkwidth, rid, xs, ys)`
+type EdgeRow = (
+    i32,
+    i32,
+    i32,
+    f64,
+    Option<i32>,
+    Option<f64>,
+    Option<i32>,
+    Option<i32>,
+    Option<String>,
+    Option<String>,
+    Option<String>,
+    Vec<f64>,
+    Vec<f64>,
+);
+
+/// A pair snapped to the network.
+#[derive(Debug, Clone, Copy)]
+struct Plan {
+    /// `pair.id`
+    id: i32,
+    from: petgraph::graph::NodeIndex,
+    to: petgraph::graph::NodeIndex,
+}
+
+/// Why a plan has no path.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Reason {
+    /// both ends snapped to the same node
+    SameNode,
+    /// no route between the nodes, such as when banned turns strand a link
+    NoRoute,
+}
+
+impl Reason {
+    fn name(&self) -> &'static str {
+        match self {
+            Reason::SameNode => "same_node",
+            Reason::NoRoute => "no_route",
+        }
+    }
+}
+
+/// Assign every pair to the road network, into `assignment.bin`, `path.bin` with the pair ids and
+/// `turn.bin` with the movements.
+#[derive(Debug, clap::Parser)]
+struct Cli {
+    /// fewest rays hitting the FGD lines for a measured width
+    #[arg(long, default_value_t = network::lanes::Options::default().min_samples)]
+    min_samples: usize,
+    /// smallest share of rays hitting the FGD lines for a measured width
+    #[arg(long, default_value_t = network::lanes::Options::default().min_coverage)]
+    min_coverage: f64,
+    /// CSV of `rid,direction` for one-way roads, `1` along the rdcl line and `-1` against it
+    #[arg(long)]
+    oneway: Option<std::path::PathBuf>,
+    /// routing cost, `time` in seconds or `lanes` for length divided by lanes
+    #[arg(long, default_value_t = network::cost::Strategy::default())]
+    cost: network::cost::Strategy,
+    /// share of the speed limit driven on a road with a single lane per direction
+    #[arg(long, default_value_t = network::cost::Model::default().single_lane, value_parser = area::parse::positive)]
+    single_lane: f64,
+    /// landmarks for the A* lower bound, saved in `landmarks.bin` and reused while they match
+    #[arg(long, default_value_t = 8)]
+    landmarks: usize,
+    /// routing threads, all cores by default
+    #[arg(long)]
+    threads: Option<usize>,
+    /// CSV of `from_rid,to_rid` for banned turns from one rdcl road into another
+    #[arg(long)]
+    turns: Option<std::path::PathBuf>,
+    /// ban U-turns except at dead ends
+    #[arg(long)]
+    no_u_turns: bool,
+    /// left turn penalty, in seconds with the `time` cost
+    #[arg(long, default_value_t = network::turn::Options::default().left_penalty, value_parser = area::parse::non_negative)]
+    left_penalty: f64,
+    /// right turn penalty, crossing the oncoming lanes
+    #[arg(long, default_value_t = network::turn::Options::default().right_penalty, value_parser = area::parse::non_negative)]
+    right_penalty: f64,
+    /// U-turn penalty
+    #[arg(long, default_value_t = network::turn::Options::default().u_turn_penalty, value_parser = area::parse::non_negative)]
+    u_turn_penalty: f64,
+    /// assignment, `aon` for free flow shortest paths, `fw` for Frank-Wolfe, `msa` or `logit`
+    #[arg(long, default_value_t = network::assign::Method::default())]
+    assign: network::assign::Method,
+    /// most equilibrium iterations
+    #[arg(long, default_value_t = network::assign::Options::default().iterations)]
+    iterations: usize,
+    /// relative gap at which the equilibrium iterations stop
+    #[arg(long, default_value_t = network::assign::Options::default().gap)]
+    gap: f64,
+    /// BPR alpha
+    #[arg(long, default_value_t = network::assign::Options::default().alpha, value_parser = area::parse::non_negative)]
+    alpha: f64,
+    /// BPR beta
+    #[arg(long, default_value_t = network::assign::Options::default().beta, value_parser = area::parse::non_negative)]
+    beta: f64,
+    /// vehicles per hour every pair stands for
+    #[arg(long, default_value_t = network::assign::Options::default().flow)]
+    flow: f64,
+    /// logit sensitivity to route costs, per second with the `time` cost
+    #[arg(long, default_value_t = network::assign::Options::default().dispersion)]
+    dispersion: f64,
+    /// alternative routes for the `logit` assignment, `penalty` or `yen` for the k cheapest
+    #[arg(long, default_value_t = network::alternative::Options::default().method)]
+    alternative_method: network::alternative::Method,
+    /// alternative routes per pair for the `logit` assignment
+    #[arg(long, default_value_t = network::alternative::Options::default().count, value_parser = area::parse::count)]
+    alternatives: usize,
+    /// factor applied to the cost of the links of every alternative found
+    #[arg(long, default_value_t = network::alternative::Options::default().penalty, value_parser = area::parse::at_least_one)]
+    alternative_penalty: f64,
+}
 
 #[tokio::main]
 async fn main() {
+    let cli = Cli::parse();
+
     #[rustfmt::skip]
     let pool = sqlx::postgres::PgPoolOptions::new()
         .connect("postgres://postgres:0@localhost/postgres")
@@ -9,38 +128,141 @@ async fn main() {
         .expect("failed to connect postgresql");
 
     #[rustfmt::skip]
-    let nodes: Vec<(i32, f64, f64)> = sqlx::query_as("SELECT id, ST_X(geom), ST_Y(geom) FROM node")
+    let nodes: Vec<(i32, f64, f64)> = sqlx::query_as("SELECT id, ST_X(geom), ST_Y(geom) FROM node ORDER BY id")
         .fetch_all(&pool)
         .await
         .unwrap();
 
     #[rustfmt::skip]
-    let edges: Vec<(i32, i32, i32, f64, Option<f64>)> = sqlx::query_as("SELECT e.id, e.n1, e.n2, e.distance, w.width FROM edge e JOIN width w ON e.id = w.id")
+    let edges: Vec<EdgeRow> = sqlx::query_as("SELECT e.id, e.n1, e.n2, e.distance, e.rdcl, w.width, w.samples, w.rays, c.rdctg, c.rnkwidth, c.rid, ARRAY(SELECT ST_X(geom) FROM ST_DumpPoints(e.geom) ORDER BY path), ARRAY(SELECT ST_Y(geom) FROM ST_DumpPoints(e.geom) ORDER BY path) FROM edge e LEFT JOIN width w ON e.id = w.id LEFT JOIN rdcl c ON e.rdcl = c.id ORDER BY e.id")
         .fetch_all(&pool)
         .await
         .unwrap();
 
-    let mut graph =
-        petgraph::Graph::<(f64, f64), (f64, u32), petgraph::Undirected>::new_undirected();
+    let network = network::Network {
+        nodes: nodes.iter().map(|node| (node.1, node.2)).collect(),
+        edges: edges
+            .iter()
+            .map(|edge| network::Edge {
+                n1: edge.1 as usize - 1,
+                n2: edge.2 as usize - 1,
+                distance: edge.3,
+                source: edge.4.unwrap_or_default(),
+            })
+            .collect(),
+    };
 
-    for node in nodes {
-        graph.add_node((node.1, node.2));
-    }
+    let oneway = match &cli.oneway {
+        Some(path) => read_oneway(path),
+        None => std::collections::HashMap::new(),
+    };
+    let oneways = edges
+        .iter()
+        .map(
+            |edge| match edge.10.as_ref().and_then(|rid| oneway.get(rid)) {
+                Some(inner) => *inner,
+                None => network::Oneway::Both,
+            },
+        )
+        .collect::<Vec<_>>();
 
-    for edge in edges {
-        let n1 = petgraph::graph::NodeIndex::new(edge.1 as usize - 1);
-        let n2 = petgraph::graph::NodeIndex::new(edge.2 as usize - 1);
-        let distance = edge.3;
-        let lane = ((edge.4.unwrap_or(f64::MAX).clamp(3.0, 18.0) / 3.0).ceil() as u32).div_ceil(2);
-        graph.add_edge(n1, n2, (distance, lane));
+    println!(
+        "[oneway stats] forward: {}, backward: {}",
+        oneways
+            .iter()
+            .filter(|o| **o == network::Oneway::Forward)
+            .count(),
+        oneways
+            .iter()
+            .filter(|o| **o == network::Oneway::Backward)
+            .count()
+    );
+
+    let evidence = edges
+        .iter()
+        .zip(&oneways)
+        .map(|(edge, oneway)| network::lanes::Evidence {
+            width: edge.5,
+            samples: edge.6.unwrap_or_default() as usize,
+            rays: edge.7.unwrap_or_default() as usize,
+            rdctg: edge.8.clone(),
+            rnkwidth: edge.9.clone(),
+            oneway: *oneway,
+        })
+        .collect::<Vec<_>>();
+
+    let options = network::lanes::Options {
+        min_samples: cli.min_samples,
+        min_coverage: cli.min_coverage,
+    };
+    let lanes = network::lanes::infer(&network, &evidence, &options);
+
+    for rule in network::lanes::Rule::ALL {
+        println!(
+            "[lanes stats] {}: {}",
+            rule.name(),
+            lanes.iter().filter(|(_, r)| *r == rule).count()
+        );
     }
 
+    let graph = network.graph(|i, forward| {
+        if !oneways[i].allows(forward) {
+            return None;
+        }
+
+        // lanes of this direction, the whole width when it is the only one
+        let rdctg = edges[i].8.as_deref();
+        let lanes = lanes[i].0;
+        Some(network::Direction {
+            lanes,
+            speed: network::class::speed(rdctg),
+            capacity: lanes as f64 * network::class::lane_capacity(rdctg),
+        })
+    });
+
     println!(
         "[graph stats] nodes: {}, edges: {}",
         graph.node_count(),
         graph.edge_count()
     );
 
+    let banned = match &cli.turns {
+        Some(path) => read_turns(path),
+        None => std::collections::HashSet::new(),
+    };
+    let rid = |link: &network::Link| edges[link.edge as usize].10.clone().unwrap_or_default();
+
+    let options = network::turn::Options {
+        u_turns: !cli.no_u_turns,
+        left_penalty: cli.left_penalty,
+        right_penalty: cli.right_penalty,
+        u_turn_penalty: cli.u_turn_penalty,
+        ..Default::default()
+    };
+    // road shape of every edge from `n1` to `n2`, for the headings at intersections
+    let shapes = edges
+        .iter()
+        .map(|edge| {
+            edge.11
+                .iter()
+                .copied()
+                .zip(edge.12.iter().copied())
+                .collect()
+        })
+        .collect::<Vec<_>>();
+
+    let (turns, stats) = network::turn::expand(
+        &graph,
+        &shapes,
+        |a, b| !banned.is_empty() && banned.contains(&(rid(a), rid(b))),
+        &options,
+    );
+
+    println!(
+        "[turn stats] movements: {}, banned: {}, u-turns: {}",
+        stats.movements, stats.banned, stats.u_turns
+    );
+
     // maximum size graph only
     let candidates = petgraph::algo::kosaraju_scc(&graph)
         .into_iter()
@@ -57,7 +279,7 @@ async fn main() {
     }
 
     #[rustfmt::skip]
-    let pairs: Vec<(i32, f64, f64, f64, f64)> = sqlx::query_as("SELECT id, ST_X(ST_StartPoint(geom)), ST_Y(ST_StartPoint(geom)), ST_X(ST_EndPoint(geom)), ST_Y(ST_EndPoint(geom)) FROM pair")
+    let pairs: Vec<(i32, f64, f64, f64, f64)> = sqlx::query_as("SELECT id, ST_X(ST_StartPoint(geom)), ST_Y(ST_StartPoint(geom)), ST_X(ST_EndPoint(geom)), ST_Y(ST_EndPoint(geom)) FROM pair ORDER BY id")
         .fetch_all(&pool)
         .await
         .unwrap();
@@ -66,59 +288,365 @@ async fn main() {
     for pair in pairs {
         let n1 = tree.nearest_neighbor(&[pair.1, pair.2]).unwrap().data;
         let n2 = tree.nearest_neighbor(&[pair.3, pair.4]).unwrap().data;
-        plans.push((n1, n2));
+        plans.push(Plan {
+            id: pair.0,
+            from: n1,
+            to: n2,
+        });
     }
 
-    println!("[plans stats] plans: {}", plans.len());
+    // plans sharing an origin are routed out of one search, ones going nowhere are not routed
+    let mut origins = std::collections::BTreeMap::<_, Vec<usize>>::new();
+    for (i, plan) in plans.iter().enumerate() {
+        if plan.from != plan.to {
+            origins.entry(plan.from).or_default().push(i);
+        }
+    }
+    let groups = origins.into_iter().collect::<Vec<_>>();
 
-    let indicator = std::sync::Arc::new(indicatif::ProgressBar::new(plans.len() as u64));
-    let share_graph = std::sync::Arc::new(graph.clone());
-    let split_len = plans.len().div_ceil(THREAD_COUNT);
-    let mut threads = vec![];
+    println!(
+        "[plans stats] plans: {}, origins: {}",
+        plans.len(),
+        groups.len()
+    );
 
-    for thread in 0..THREAD_COUNT {
-        let indicator = indicator.clone();
-        let graph = share_graph.clone();
-        let plans = plans
-            .iter()
-            .skip(split_len * thread)
-            .take(split_len)
-            .cloned()
-            .collect::<Vec<_>>();
+    let model = network::cost::Model {
+        strategy: cli.cost,
+        single_lane: cli.single_lane,
+    };
+    let heuristic = network::cost::Heuristic::new(&graph, &model);
 
-        let thread = std::thread::spawn(move || {
-            plans
-                .into_iter()
-                .filter_map(|plan| {
-                    indicator.inc(1);
-
-                    petgraph::algo::astar(
-                        graph.as_ref(),
-                        plan.0,
-                        |n| n == plan.1,
-                        |e| e.weight().0 / e.weight().1 as f64,
-                        |_| 0.0,
-                    )
-                })
-                .map(|path| {
-                    path.1
-                        .into_iter()
-                        .map(|n| n.index() as u32)
+    let (landmarks, reused) = network::alt::Landmarks::load_or_build(
+        std::path::Path::new("landmarks.bin"),
+        &graph,
+        cli.landmarks,
+        |link| model.cost(link),
+    );
+    println!(
+        "[landmark stats] landmarks: {}, reused: {}",
+        landmarks.nodes.len(),
+        reused
+    );
+
+    // all cores unless overridden
+    let threads = rayon::ThreadPoolBuilder::new()
+        .num_threads(cli.threads.unwrap_or(0))
+        .build()
+        .unwrap();
+    println!("[thread stats] threads: {}", threads.current_num_threads());
+
+    let indicator = indicatif::ProgressBar::new(plans.len() as u64);
+
+    // links of every plan under the given link costs, turn penalties added by the search
+    let route = |costs: &[f64]| {
+        indicator.set_position(0);
+
+        let routed = threads.install(|| {
+            groups
+                .par_iter()
+                .flat_map_iter(|(origin, members)| {
+                    let mut destinations = members.iter().map(|i| plans[*i].to).collect::<Vec<_>>();
+                    destinations.sort();
+                    destinations.dedup();
+
+                    // congested costs never fall below free flow ones with alpha and beta of zero or
+                    // more, so the bounds still hold
+                    let paths = match destinations.as_slice() {
+                        [destination] => vec![turns.astar(
+                            &graph,
+                            *origin,
+                            *destination,
+                            |edge, _| costs[edge.index()],
+                            |n| {
+                                let bound = landmarks.estimate(n, *destination);
+                                bound.max(heuristic.estimate(&graph, n, *destination))
+                            },
+                        )],
+                        _ => turns.tree(&graph, *origin, &destinations, |edge, _| {
+                            costs[edge.index()]
+                        }),
+                    };
+
+                    indicator.inc(members.len() as u64);
+
+                    members
+                        .iter()
+                        .map(|i| {
+                            let j = destinations.binary_search(&plans[*i].to).unwrap();
+                            (*i, paths[j].as_ref().map(|(_, links)| links.clone()))
+                        })
                         .collect::<Vec<_>>()
                 })
                 .collect::<Vec<_>>()
         });
-        threads.push(thread);
-    }
 
-    let paths = threads
-        .into_iter()
-        .flat_map(|thread| thread.join().unwrap())
+        // route `i` is plan `i`
+        let mut routes = vec![None; plans.len()];
+        for (i, links) in routed {
+            routes[i] = links;
+        }
+        routes
+    };
+
+    let options = network::assign::Options {
+        method: cli.assign,
+        alpha: cli.alpha,
+        beta: cli.beta,
+        flow: cli.flow,
+        iterations: cli.iterations,
+        gap: cli.gap,
+        dispersion: cli.dispersion,
+    };
+    let free = graph
+        .edge_weights()
+        .map(|link| model.cost(link))
         .collect::<Vec<_>>();
 
+    let mut assignment = match options.method {
+        network::assign::Method::Logit => {
+            let alternatives = network::alternative::Options {
+                method: cli.alternative_method,
+                count: cli.alternatives,
+                penalty: cli.alternative_penalty,
+            };
+
+            // alternatives are found once on free flow costs
+            let routes = threads.install(|| {
+                plans
+                    .par_iter()
+                    .map(|plan| {
+                        if plan.from == plan.to {
+                            indicator.inc(1);
+                            return vec![];
+                        }
+
+                        let routes = network::alternative::routes(
+                            &turns,
+                            &graph,
+                            plan.from,
+                            plan.to,
+                            |_, link| model.cost(link),
+                            |n| {
+                                let bound = landmarks.estimate(n, plan.to);
+                                bound.max(heuristic.estimate(&graph, n, plan.to))
+                            },
+                            &alternatives,
+                        );
+                        indicator.inc(1);
+                        routes
+                    })
+                    .collect::<Vec<_>>()
+            });
+
+            // overlap of every alternative with the first route of its plan
+            let overlaps = routes
+                .iter()
+                .flat_map(|routes| {
+                    routes
+                        .iter()
+                        .skip(1)
+                        .map(|route| route.overlap(&routes[0], &graph))
+                })
+                .collect::<Vec<_>>();
+
+            println!(
+                "[alternative stats] method: {}, routes: {}, per plan: {:.2}, overlap: {:.3}",
+                alternatives.method,
+                routes.iter().map(|routes| routes.len()).sum::<usize>(),
+                routes.iter().map(|routes| routes.len()).sum::<usize>() as f64
+                    / plans.len().max(1) as f64,
+                overlaps.iter().sum::<f64>() / overlaps.len().max(1) as f64
+            );
+
+            network::assign::stochastic(&graph, &free, &options, &routes)
+        }
+        _ => network::assign::assign(&graph, &free, &turns, &options, route),
+    };
+    assignment.ids = plans.iter().map(|plan| plan.id).collect();
+
     indicator.finish();
-    println!("[path stats] paths: {}", paths.len());
+    for (k, iteration) in assignment.iterations.iter().enumerate() {
+        println!(
+            "[assign stats] iteration: {}, gap: {:.6}, step: {:.4}",
+            k + 1,
+            iteration.gap,
+            iteration.step
+        );
+    }
+    println!(
+        "[assign stats] method: {}, volume: {:.0}, over capacity: {}",
+        options.method,
+        assignment.volumes.iter().sum::<f64>(),
+        graph
+            .edge_weights()
+            .zip(&assignment.volumes)
+            .filter(|(link, v)| **v > link.capacity)
+            .count()
+    );
+
+    // path `i` is plan `i`, whose pair id is `assignment.ids[i]`, as the graph edge indices of the
+    // route carrying most of its flow, empty when it was not routed
+    let paths = assignment.main_paths();
+
+    let unroutable = plans
+        .iter()
+        .zip(&paths)
+        .filter_map(|(plan, path)| match path.is_empty() {
+            true if plan.from == plan.to => Some((plan, Reason::SameNode)),
+            true => Some((plan, Reason::NoRoute)),
+            false => None,
+        })
+        .collect::<Vec<_>>();
+
+    println!(
+        "[path stats] paths: {}, same node: {}, no route: {}",
+        paths.iter().filter(|path| !path.is_empty()).count(),
+        unroutable
+            .iter()
+            .filter(|(_, reason)| *reason == Reason::SameNode)
+            .count(),
+        unroutable
+            .iter()
+            .filter(|(_, reason)| *reason == Reason::NoRoute)
+            .count()
+    );
+
+    #[rustfmt::skip]
+    sqlx::query("DROP TABLE IF EXISTS unroutable")
+        .execute(&pool)
+        .await
+        .unwrap();
+
+    #[rustfmt::skip]
+    sqlx::query("CREATE TABLE IF NOT EXISTS unroutable (id Int4 REFERENCES pair (id), reason Text, n1 Int4, n2 Int4)")
+        .execute(&pool)
+        .await
+        .unwrap();
+
+    let ids = unroutable.iter().map(|(p, _)| p.id).collect::<Vec<_>>();
+    let reasons = unroutable
+        .iter()
+        .map(|(_, r)| r.name().to_string())
+        .collect::<Vec<_>>();
+    let n1s = unroutable
+        .iter()
+        .map(|(p, _)| p.from.index() as i32 + 1)
+        .collect::<Vec<_>>();
+    let n2s = unroutable
+        .iter()
+        .map(|(p, _)| p.to.index() as i32 + 1)
+        .collect::<Vec<_>>();
+
+    #[rustfmt::skip]
+    sqlx::query("INSERT INTO unroutable (id, reason, n1, n2) SELECT * FROM unnest($1, $2, $3, $4)")
+        .bind(&ids)
+        .bind(&reasons)
+        .bind(&n1s)
+        .bind(&n2s)
+        .execute(&pool)
+        .await
+        .unwrap();
+
+    let bytes = postcard::to_extend(&assignment, vec![]).unwrap();
+    std::fs::write("assignment.bin", bytes).unwrap();
 
-    let bytes = postcard::to_extend(&(graph, paths), vec![]).unwrap();
+    let bytes = postcard::to_extend(&(graph, paths, &assignment.ids), vec![]).unwrap();
     std::fs::write("path.bin", bytes).unwrap();
+
+    let bytes = postcard::to_extend(&turns, vec![]).unwrap();
+    std::fs::write("turn.bin", bytes).unwrap();
+}
+
+/// One-way roads by rdcl `rID`, rows with another direction such as a header are skipped.
+fn read_oneway(path: &std::path::Path) -> std::collections::HashMap<String, network::Oneway> {
+    let text = std::fs::read_to_string(path).expect("failed to read oneway csv");
+
+    let mut oneway = std::collections::HashMap::new();
+    for line in text.lines() {
+        let (rid, direction) = match line.split_once(',') {
+            Some(inner) => inner,
+            None => continue,
+        };
+
+        let direction = match direction.trim() {
+            "1" => network::Oneway::Forward,
+            "-1" => network::Oneway::Backward,
+            _ => continue,
+        };
+        oneway.insert(rid.trim().to_string(), direction);
+    }
+
+    oneway
+}
+
+/// Banned turns by rdcl `rID`, a header row matches no road.
+fn read_turns(path: &std::path::Path) -> std::collections::HashSet<(String, String)> {
+    let text = std::fs::read_to_string(path).expect("failed to read turns csv");
+
+    let mut banned = std::collections::HashSet::new();
+    for line in text.lines() {
+        let (from, to) = match line.split_once(',') {
+            Some(inner) => inner,
+            None => continue,
+        };
+        banned.insert((from.trim().to_string(), to.trim().to_string()));
+    }
+
+    banned
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use petgraph::graph::NodeIndex;
+
+    #[test]
+    fn keeps_pair_ids_aligned_past_an_unroutable_pair() {
+        // three nodes in a row and a fourth one on its own
+        let network = network::testing::network(
+            &[(0.0, 0.0), (0.001, 0.0), (0.002, 0.0), (0.01, 0.01)],
+            &[(0, 1), (1, 2)],
+        );
+        let graph = network::testing::graph(&network, network::Oneway::Both, 1000.0);
+        let (turns, _) = network::turn::expand(&graph, &[], |_, _| false, &Default::default());
+
+        let plan = |id, from, to| Plan {
+            id,
+            from: NodeIndex::new(from),
+            to: NodeIndex::new(to),
+        };
+        let plans = [plan(10, 0, 2), plan(20, 0, 3), plan(30, 2, 0)];
+
+        let route = |costs: &[f64]| {
+            plans
+                .iter()
+                .map(|plan| {
+                    let path = turns.astar(
+                        &graph,
+                        plan.from,
+                        plan.to,
+                        |edge, _| costs[edge.index()],
+                        |_| 0.0,
+                    );
+                    path.map(|(_, links)| links)
+                })
+                .collect()
+        };
+        let free = graph.edge_weights().map(|_| 10.0).collect::<Vec<_>>();
+        let mut assignment =
+            network::assign::assign(&graph, &free, &turns, &Default::default(), route);
+        assignment.ids = plans.iter().map(|plan| plan.id).collect();
+
+        let paths = assignment.main_paths();
+        let bytes = postcard::to_extend(&(graph, paths, &assignment.ids), vec![]).unwrap();
+        let (_, paths, ids): (network::Graph, Vec<Vec<u32>>, Vec<i32>) =
+            postcard::from_bytes(&bytes).unwrap();
+
+        assert_eq!(ids, vec![10, 20, 30]);
+        // links are the edges forward then backward, in edge order
+        assert_eq!(paths[0], vec![0, 2]);
+        assert!(paths[1].is_empty());
+        assert_eq!(paths[2], vec![3, 1]);
+    }
 }
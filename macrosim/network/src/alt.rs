@@ -0,0 +1,149 @@
+//! Landmark lower bounds for A* (ALT).
+//!
+//! The cheapest cost from and to a few landmarks spread over the network is computed once, and by
+//! the triangle inequality `d(l, t) - d(l, n)` and `d(n, l) - d(t, l)` bound the cost from `n` to
+//! `t` from below. Turn penalties only add cost, so the bounds hold for the search over movements
+//! too. The tables depend on the link costs and are meant to be saved and reused while they match.
+
+use petgraph::visit::EdgeRef;
+
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct Landmarks {
+    /// hash of the graph and the link costs the tables were built for
+    pub checksum: u64,
+    /// landmarks asked for, fewer are picked when the rest of the network is unreachable
+    pub count: usize,
+    pub nodes: Vec<u32>,
+    /// cost from every landmark to every node
+    from: Vec<Vec<f64>>,
+    /// cost from every node to every landmark
+    to: Vec<Vec<f64>>,
+}
+
+/// Hash of the links of the graph and their costs.
+pub fn checksum(graph: &crate::Graph, cost: impl Fn(&crate::Link) -> f64) -> u64 {
+    let mut hasher = std::collections::hash_map::DefaultHasher::new();
+    std::hash::Hash::hash(&graph.node_count(), &mut hasher);
+    for edge in graph.edge_references() {
+        std::hash::Hash::hash(&(edge.source().index(), edge.target().index()), &mut hasher);
+        std::hash::Hash::hash(&cost(edge.weight()).to_bits(), &mut hasher);
+    }
+    std::hash::Hasher::finish(&hasher)
+}
+
+fn distances(
+    graph: impl petgraph::visit::IntoEdges<NodeId = petgraph::graph::NodeIndex, EdgeWeight = crate::Link>
+        + petgraph::visit::Visitable
+        + petgraph::visit::NodeCount,
+    landmark: petgraph::graph::NodeIndex,
+    cost: impl Fn(&crate::Link) -> f64,
+) -> Vec<f64> {
+    let mut distances = vec![f64::INFINITY; graph.node_count()];
+    for (n, d) in petgraph::algo::dijkstra(graph, landmark, None, |e| cost(e.weight())) {
+        distances[n.index()] = d;
+    }
+    distances
+}
+
+impl Landmarks {
+    /// Pick `count` landmarks, each the node farthest from the ones already picked, starting from
+    /// the node farthest from the first node with links.
+    pub fn build(
+        graph: &crate::Graph,
+        count: usize,
+        cost: impl Fn(&crate::Link) -> f64 + Sync,
+    ) -> Self {
+        let mut landmarks = Landmarks {
+            checksum: checksum(graph, &cost),
+            count,
+            nodes: vec![],
+            from: vec![],
+            to: vec![],
+        };
+
+        let start = match graph.edge_references().next() {
+            Some(edge) => edge.source(),
+            None => return landmarks,
+        };
+
+        // cost from the nearest picked landmark, nodes unreachable from the start are never picked
+        let mut nearest = distances(graph, start, &cost)
+            .into_iter()
+            .map(|d| if d.is_finite() { d } else { f64::NEG_INFINITY })
+            .collect::<Vec<_>>();
+
+        for _ in 0..count {
+            let (landmark, d) = nearest
+                .iter()
+                .enumerate()
+                .max_by(|a, b| a.1.total_cmp(b.1))
+                .map(|(n, d)| (n, *d))
+                .unwrap();
+            if d == f64::NEG_INFINITY || landmarks.nodes.contains(&(landmark as u32)) {
+                break;
+            }
+
+            let landmark = petgraph::graph::NodeIndex::new(landmark);
+            let (from, to) = std::thread::scope(|scope| {
+                let from = scope.spawn(|| distances(graph, landmark, &cost));
+                let to =
+                    scope.spawn(|| distances(petgraph::visit::Reversed(graph), landmark, &cost));
+                (from.join().unwrap(), to.join().unwrap())
+            });
+
+            let first = landmarks.nodes.is_empty();
+            for (n, d) in from.iter().enumerate() {
+                if nearest[n] != f64::NEG_INFINITY && d.is_finite() {
+                    nearest[n] = if first { *d } else { nearest[n].min(*d) };
+                }
+            }
+
+            landmarks.nodes.push(landmark.index() as u32);
+            landmarks.from.push(from);
+            landmarks.to.push(to);
+        }
+
+        landmarks
+    }
+
+    /// The landmarks saved at `path` if they were built for this graph and these costs, otherwise
+    /// newly built ones saved there, and whether they were reused.
+    pub fn load_or_build(
+        path: &std::path::Path,
+        graph: &crate::Graph,
+        count: usize,
+        cost: impl Fn(&crate::Link) -> f64 + Sync,
+    ) -> (Self, bool) {
+        let saved = std::fs::read(path)
+            .ok()
+            .and_then(|bytes| postcard::from_bytes::<Landmarks>(&bytes).ok());
+
+        match saved {
+            Some(inner) if inner.count == count && inner.checksum == checksum(graph, &cost) => {
+                (inner, true)
+            }
+            _ => {
+                let landmarks = Landmarks::build(graph, count, cost);
+                let bytes = postcard::to_extend(&landmarks, vec![]).unwrap();
+                std::fs::write(path, bytes).unwrap();
+                (landmarks, false)
+            }
+        }
+    }
+
+    /// Lower bound of the cost from `n` to `target`.
+    pub fn estimate(
+        &self,
+        n: petgraph::graph::NodeIndex,
+        target: petgraph::graph::NodeIndex,
+    ) -> f64 {
+        let (n, t) = (n.index(), target.index());
+
+        self.from
+            .iter()
+            .zip(self.to.iter())
+            .map(|(from, to)| (from[t] - from[n]).max(to[n] - to[t]))
+            .filter(|bound| !bound.is_nan())
+            .fold(0.0, f64::max)
+    }
+}
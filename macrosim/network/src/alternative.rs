@@ -0,0 +1,532 @@
+//! Alternative routes between two nodes.
+//!
+//! The penalty method routes again and again over the movements, making every link of the routes
+//! found more expensive by a factor each time, so later routes leave them where a detour is cheap.
+//! Yen's method finds the `k` cheapest routes which do not visit a node twice, by deviating from
+//! every node of the routes already found. The costs of the routes returned are the true ones,
+//! without the penalties.
+
+use crate::turn::{State, Turns};
+use crate::{Graph, Link};
+
+use petgraph::visit::EdgeRef;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Method {
+    #[default]
+    Penalty,
+    Yen,
+}
+
+impl std::str::FromStr for Method {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "penalty" => Ok(Method::Penalty),
+            "yen" => Ok(Method::Yen),
+            _ => Err(format!(
+                "unknown alternative method {}, expected penalty or yen",
+                s
+            )),
+        }
+    }
+}
+
+impl std::fmt::Display for Method {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Method::Penalty => write!(f, "penalty"),
+            Method::Yen => write!(f, "yen"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct Options {
+    pub method: Method,
+    /// routes wanted, fewer are returned when there are no more to find
+    pub count: usize,
+    /// factor applied to the cost of a link every time a route travels it, for the penalty method
+    pub penalty: f64,
+}
+
+impl Default for Options {
+    fn default() -> Self {
+        Self {
+            method: Method::Penalty,
+            count: 3,
+            penalty: 1.5,
+        }
+    }
+}
+
+/// A route as the links travelled.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Route {
+    pub from: petgraph::graph::NodeIndex,
+    pub links: Vec<petgraph::graph::EdgeIndex>,
+    /// link costs and turn penalties
+    pub cost: f64,
+    /// turn penalties alone, which do not change with the link costs
+    pub penalty: f64,
+    /// length in metres
+    pub distance: f64,
+}
+
+impl Route {
+    pub fn new(
+        turns: &Turns,
+        graph: &Graph,
+        from: petgraph::graph::NodeIndex,
+        links: Vec<petgraph::graph::EdgeIndex>,
+        cost: impl Fn(petgraph::graph::EdgeIndex, &Link) -> f64,
+    ) -> Self {
+        let penalty = links
+            .windows(2)
+            .map(|pair| turns.penalty(pair[0], pair[1]).unwrap_or_default())
+            .sum::<f64>();
+        let cost = links.iter().map(|l| cost(*l, &graph[*l])).sum::<f64>() + penalty;
+        let distance = links.iter().map(|l| graph[*l].distance).sum::<f64>();
+
+        Self {
+            from,
+            links,
+            cost,
+            penalty,
+            distance,
+        }
+    }
+
+    /// Cost of the route under other link costs, by graph edge index.
+    pub fn cost_with(&self, costs: &[f64]) -> f64 {
+        self.links.iter().map(|l| costs[l.index()]).sum::<f64>() + self.penalty
+    }
+
+    /// Node indices travelled.
+    pub fn nodes(&self, graph: &Graph) -> Vec<u32> {
+        crate::nodes(graph, self.from, &self.links)
+    }
+
+    /// Share of the length of the shorter route travelled by both, one for the same edges.
+    pub fn overlap(&self, other: &Route, graph: &Graph) -> f64 {
+        let edges = |route: &Route| {
+            route
+                .links
+                .iter()
+                .map(|l| graph[*l].edge)
+                .collect::<std::collections::HashSet<_>>()
+        };
+        let (a, b) = (edges(self), edges(other));
+        let shared = a
+            .intersection(&b)
+            .map(|edge| {
+                let link = self
+                    .links
+                    .iter()
+                    .find(|l| graph[**l].edge == *edge)
+                    .unwrap();
+                graph[*link].distance
+            })
+            .sum::<f64>();
+
+        match self.distance.min(other.distance) {
+            shorter if shorter > 0.0 => shared / shorter,
+            _ => 1.0,
+        }
+    }
+}
+
+/// Alternative routes from `from` to `to` by the method of the options, cheapest first for Yen's.
+pub fn routes(
+    turns: &Turns,
+    graph: &Graph,
+    from: petgraph::graph::NodeIndex,
+    to: petgraph::graph::NodeIndex,
+    cost: impl Fn(petgraph::graph::EdgeIndex, &Link) -> f64,
+    heuristic: impl Fn(petgraph::graph::NodeIndex) -> f64,
+    options: &Options,
+) -> Vec<Route> {
+    match options.method {
+        Method::Penalty => penalty(turns, graph, from, to, cost, heuristic, options),
+        Method::Yen => yen(turns, graph, from, to, cost, heuristic, options.count),
+    }
+}
+
+/// Distinct routes from `from` to `to` by the penalty method, in the order found.
+///
+/// Panics unless the penalty factor is one or more.
+pub fn penalty(
+    turns: &Turns,
+    graph: &Graph,
+    from: petgraph::graph::NodeIndex,
+    to: petgraph::graph::NodeIndex,
+    cost: impl Fn(petgraph::graph::EdgeIndex, &Link) -> f64,
+    heuristic: impl Fn(petgraph::graph::NodeIndex) -> f64,
+    options: &Options,
+) -> Vec<Route> {
+    // penalties only add cost, so the heuristic stays a lower bound
+    assert!(options.penalty >= 1.0, "penalty must be one or more");
+    let mut factors = std::collections::HashMap::<usize, f64>::new();
+    let mut routes = Vec::<Route>::new();
+
+    for _ in 0..options.count * 2 {
+        if routes.len() >= options.count {
+            break;
+        }
+
+        let penalized = |link: petgraph::graph::EdgeIndex, weight: &Link| {
+            cost(link, weight) * factors.get(&link.index()).unwrap_or(&1.0)
+        };
+        let links = match turns.astar(graph, from, to, penalized, &heuristic) {
+            Some((_, inner)) => inner,
+            None => break,
+        };
+
+        for link in &links {
+            *factors.entry(link.index()).or_insert(1.0) *= options.penalty;
+        }
+
+        if routes.iter().any(|route| route.links == links) {
+            continue;
+        }
+
+        let empty = links.is_empty();
+        routes.push(Route::new(turns, graph, from, links, &cost));
+        if empty {
+            break;
+        }
+    }
+
+    routes
+}
+
+/// The `count` cheapest routes from `from` to `to` visiting no node twice, cheapest first.
+pub fn yen(
+    turns: &Turns,
+    graph: &Graph,
+    from: petgraph::graph::NodeIndex,
+    to: petgraph::graph::NodeIndex,
+    cost: impl Fn(petgraph::graph::EdgeIndex, &Link) -> f64,
+    heuristic: impl Fn(petgraph::graph::NodeIndex) -> f64,
+    count: usize,
+) -> Vec<Route> {
+    if count == 0 {
+        return vec![];
+    }
+
+    let first = match turns.astar(graph, from, to, &cost, &heuristic) {
+        Some((_, inner)) => Route::new(turns, graph, from, inner, &cost),
+        None => return vec![],
+    };
+    if first.links.is_empty() {
+        return vec![first];
+    }
+
+    let mut routes = vec![first];
+    let mut candidates = Vec::<Route>::new();
+
+    while routes.len() < count {
+        let last = routes.last().unwrap();
+        let nodes = last.nodes(graph);
+
+        for i in 0..last.links.len() {
+            let root = &last.links[..i];
+
+            // links leaving the spur node along the routes sharing this root
+            let removed = routes
+                .iter()
+                .filter(|route| route.links.len() > i && route.links[..i] == *root)
+                .map(|route| route.links[i].index())
+                .collect::<std::collections::HashSet<_>>();
+            let visited = nodes[..=i]
+                .iter()
+                .map(|n| *n as usize)
+                .collect::<std::collections::HashSet<_>>();
+
+            let spur = match spur(
+                turns,
+                graph,
+                petgraph::graph::NodeIndex::new(nodes[i] as usize),
+                root.last().copied(),
+                to,
+                &cost,
+                &removed,
+                &visited,
+            ) {
+                Some(inner) => inner,
+                None => continue,
+            };
+
+            let links = root.iter().copied().chain(spur).collect::<Vec<_>>();
+            let known = |route: &Route| route.links == links;
+            if routes.iter().any(known) || candidates.iter().any(known) {
+                continue;
+            }
+            candidates.push(Route::new(turns, graph, from, links, &cost));
+        }
+
+        let cheapest = match candidates
+            .iter()
+            .enumerate()
+            .min_by(|a, b| a.1.cost.total_cmp(&b.1.cost))
+        {
+            Some((j, _)) => j,
+            None => break,
+        };
+        routes.push(candidates.swap_remove(cheapest));
+    }
+
+    routes
+}
+
+/// Cheapest links from `spur` to `to`, entered through `via` when given, without the `removed`
+/// links and without entering the `visited` nodes.
+#[allow(clippy::too_many_arguments)]
+fn spur(
+    turns: &Turns,
+    graph: &Graph,
+    spur: petgraph::graph::NodeIndex,
+    via: Option<petgraph::graph::EdgeIndex>,
+    to: petgraph::graph::NodeIndex,
+    cost: impl Fn(petgraph::graph::EdgeIndex, &Link) -> f64,
+    removed: &std::collections::HashSet<usize>,
+    visited: &std::collections::HashSet<usize>,
+) -> Option<Vec<petgraph::graph::EdgeIndex>> {
+    let allowed = |link: petgraph::graph::EdgeIndex| {
+        let (_, head) = graph.edge_endpoints(link).unwrap();
+        !removed.contains(&link.index()) && !visited.contains(&head.index())
+    };
+
+    let start = match via {
+        Some(inner) => turns.next[inner.index()]
+            .iter()
+            .map(|(next, penalty)| (petgraph::graph::EdgeIndex::new(*next as usize), *penalty))
+            .collect::<Vec<_>>(),
+        None => graph.edges(spur).map(|edge| (edge.id(), 0.0)).collect(),
+    };
+
+    let mut costs = std::collections::HashMap::<u32, f64>::new();
+    let mut previous = std::collections::HashMap::<u32, u32>::new();
+    let mut heap = std::collections::BinaryHeap::new();
+
+    for (link, penalty) in start {
+        if !allowed(link) {
+            continue;
+        }
+        let c = penalty + cost(link, &graph[link]);
+        let link = link.index() as u32;
+        if c < *costs.get(&link).unwrap_or(&f64::INFINITY) {
+            costs.insert(link, c);
+            heap.push(State {
+                score: c,
+                cost: c,
+                link,
+            });
+        }
+    }
+
+    while let Some(State {
+        cost: current,
+        link,
+        ..
+    }) = heap.pop()
+    {
+        if current > costs[&link] {
+            continue;
+        }
+        let index = petgraph::graph::EdgeIndex::new(link as usize);
+        let (_, head) = graph.edge_endpoints(index).unwrap();
+
+        if head == to {
+            let mut links = vec![index];
+            let mut link = link;
+            while let Some(inner) = previous.get(&link) {
+                link = *inner;
+                links.push(petgraph::graph::EdgeIndex::new(link as usize));
+            }
+            links.reverse();
+            return Some(links);
+        }
+
+        for &(next, penalty) in &turns.next[link as usize] {
+            let edge = petgraph::graph::EdgeIndex::new(next as usize);
+            if !allowed(edge) {
+                continue;
+            }
+            let c = current + penalty + cost(edge, &graph[edge]);
+            if c < *costs.get(&next).unwrap_or(&f64::INFINITY) {
+                costs.insert(next, c);
+                previous.insert(next, link);
+                heap.push(State {
+                    score: c,
+                    cost: c,
+                    link: next,
+                });
+            }
+        }
+    }
+
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use petgraph::graph::{EdgeIndex, NodeIndex};
+
+    /// Square `0 1 / 2 3` with a diagonal from 1 to 2, the edges `0 -> 1`, `1 -> 3`, `0 -> 2`,
+    /// `2 -> 3` and `1 -> 2` costing 1, 2, 2, 2.5 and 0.5.
+    fn square(oneway: crate::Oneway) -> (Graph, impl Fn(EdgeIndex, &Link) -> f64) {
+        let network = crate::testing::network(
+            &[(0.0, 0.0), (0.001, 0.0), (0.0, 0.001), (0.001, 0.001)],
+            &[(0, 1), (1, 3), (0, 2), (2, 3), (1, 2)],
+        );
+        let graph = crate::testing::graph(&network, oneway, 1000.0);
+        let cost = |_: EdgeIndex, link: &Link| [1.0, 2.0, 2.0, 2.5, 0.5][link.edge as usize];
+        (graph, cost)
+    }
+
+    /// Every movement without penalties, except the ones `banned` returns true for.
+    fn turns(graph: &Graph, banned: impl Fn(&Link, &Link) -> bool) -> Turns {
+        let options = crate::turn::Options {
+            left_penalty: 0.0,
+            right_penalty: 0.0,
+            u_turn_penalty: 0.0,
+            ..Default::default()
+        };
+        crate::turn::expand(graph, &[], banned, &options).0
+    }
+
+    fn links(routes: &[Route]) -> Vec<Vec<usize>> {
+        routes
+            .iter()
+            .map(|route| route.links.iter().map(|l| l.index()).collect())
+            .collect()
+    }
+
+    #[test]
+    fn finds_the_cheapest_routes_in_order() {
+        let (graph, cost) = square(crate::Oneway::Forward);
+        let turns = turns(&graph, |_, _| false);
+        let routes = yen(
+            &turns,
+            &graph,
+            NodeIndex::new(0),
+            NodeIndex::new(3),
+            cost,
+            |_| 0.0,
+            3,
+        );
+
+        assert_eq!(links(&routes), vec![vec![0, 1], vec![0, 4, 3], vec![2, 3]]);
+        let costs = routes.iter().map(|route| route.cost).collect::<Vec<_>>();
+        assert_eq!(costs, vec![3.0, 4.0, 4.5]);
+    }
+
+    #[test]
+    fn returns_every_route_without_a_repeated_node_when_fewer_exist() {
+        // U-turns are allowed, so only the search keeps the routes from going back
+        let (graph, cost) = square(crate::Oneway::Both);
+        let turns = turns(&graph, |_, _| false);
+        let routes = yen(
+            &turns,
+            &graph,
+            NodeIndex::new(0),
+            NodeIndex::new(3),
+            cost,
+            |_| 0.0,
+            10,
+        );
+
+        // 0 1 3, 0 1 2 3, 0 2 3 and 0 2 1 3
+        assert_eq!(routes.len(), 4);
+        for route in &routes {
+            let nodes = route.nodes(&graph);
+            let unique = nodes.iter().collect::<std::collections::HashSet<_>>();
+            assert_eq!(unique.len(), nodes.len(), "{:?} repeats a node", nodes);
+            assert_eq!(nodes.last(), Some(&3));
+        }
+        assert!(routes.windows(2).all(|pair| pair[0].cost <= pair[1].cost));
+    }
+
+    #[test]
+    fn leaves_the_spur_node_by_an_allowed_movement() {
+        // `0 -> 1 -> 3` is banned, so no spur from node 1 may take it after entering by `0 -> 1`
+        let (graph, cost) = square(crate::Oneway::Forward);
+        let turns = turns(&graph, |a, b| a.edge == 0 && b.edge == 1);
+        let routes = yen(
+            &turns,
+            &graph,
+            NodeIndex::new(0),
+            NodeIndex::new(3),
+            cost,
+            |_| 0.0,
+            3,
+        );
+
+        assert_eq!(links(&routes), vec![vec![0, 4, 3], vec![2, 3]]);
+    }
+
+    #[test]
+    fn finds_nothing_between_unconnected_nodes() {
+        let (graph, cost) = square(crate::Oneway::Forward);
+        let turns = turns(&graph, |_, _| false);
+        let routes = yen(
+            &turns,
+            &graph,
+            NodeIndex::new(3),
+            NodeIndex::new(0),
+            cost,
+            |_| 0.0,
+            3,
+        );
+
+        assert!(routes.is_empty());
+    }
+
+    #[test]
+    fn penalises_routes_into_distinct_ones_at_their_true_costs() {
+        let (graph, cost) = square(crate::Oneway::Forward);
+        let turns = turns(&graph, |_, _| false);
+        let options = Options {
+            penalty: 2.0,
+            ..Default::default()
+        };
+        let routes = penalty(
+            &turns,
+            &graph,
+            NodeIndex::new(0),
+            NodeIndex::new(3),
+            cost,
+            |_| 0.0,
+            &options,
+        );
+
+        // `0 4 3` is never the cheapest once its links carry the factor, and the tries run out
+        assert_eq!(links(&routes), vec![vec![0, 1], vec![2, 3]]);
+        let costs = routes.iter().map(|route| route.cost).collect::<Vec<_>>();
+        assert_eq!(costs, vec![3.0, 4.5]);
+    }
+
+    #[test]
+    #[should_panic(expected = "penalty must be one or more")]
+    fn rejects_a_penalty_below_one() {
+        let (graph, cost) = square(crate::Oneway::Forward);
+        let turns = turns(&graph, |_, _| false);
+        let options = Options {
+            penalty: 0.5,
+            ..Default::default()
+        };
+        penalty(
+            &turns,
+            &graph,
+            NodeIndex::new(0),
+            NodeIndex::new(3),
+            cost,
+            |_| 0.0,
+            &options,
+        );
+    }
+}
@@ -0,0 +1,525 @@
+//! User equilibrium traffic assignment.
+//!
+//! Link costs grow with their volume following the BPR function
+//! `t = t0 (1 + alpha (v / c) ^ beta)`, with `c` the capacity of the link. Every iteration assigns
+//! all plans all-or-nothing to their cheapest paths under the current costs and moves the volumes
+//! towards that assignment, by a line search on the Beckmann objective (Frank-Wolfe) or by
+//! `1 / (k + 1)` (MSA), until the relative gap is small enough.
+//!
+//! The gap is taken over the paths of the plans, `sum(f (c - c*)) / sum(f c)` with `f` the flow on
+//! a path, `c` its cost and `c*` the cost of the cheapest path of its plan, both with the turn
+//! penalties. The penalties do not depend on volumes, so they add a constant to the derivative of
+//! the line search. A gap which is not positive while the all-or-nothing volumes still differ from
+//! the current ones is not taken as converged.
+//!
+//! The stochastic assignment instead splits every plan between a fixed set of alternative routes
+//! by a logit model on their current costs, averaging the splits by MSA. Its gap is the relative
+//! change of the volumes, `sum(|y - v|) / sum(v)` with `y` the logit volumes.
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Method {
+    /// every plan on its free flow cheapest path
+    AllOrNothing,
+    #[default]
+    FrankWolfe,
+    Msa,
+    /// logit split between alternative routes
+    Logit,
+}
+
+impl std::str::FromStr for Method {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "aon" => Ok(Method::AllOrNothing),
+            "fw" => Ok(Method::FrankWolfe),
+            "msa" => Ok(Method::Msa),
+            "logit" => Ok(Method::Logit),
+            _ => Err(format!(
+                "unknown assignment method {}, expected aon, fw, msa or logit",
+                s
+            )),
+        }
+    }
+}
+
+impl std::fmt::Display for Method {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Method::AllOrNothing => write!(f, "aon"),
+            Method::FrankWolfe => write!(f, "fw"),
+            Method::Msa => write!(f, "msa"),
+            Method::Logit => write!(f, "logit"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct Options {
+    pub method: Method,
+    pub alpha: f64,
+    pub beta: f64,
+    /// vehicles per hour every plan stands for
+    pub flow: f64,
+    pub iterations: usize,
+    /// relative gap at which the iterations stop
+    pub gap: f64,
+    /// logit sensitivity to route costs, per unit of cost
+    pub dispersion: f64,
+}
+
+impl Default for Options {
+    fn default() -> Self {
+        Self {
+            method: Method::FrankWolfe,
+            alpha: 0.15,
+            beta: 4.0,
+            flow: 1.0,
+            iterations: 50,
+            gap: 1e-4,
+            dispersion: 0.1,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, serde::Serialize, serde::Deserialize)]
+pub struct Iteration {
+    /// relative gap of the path costs before the step
+    pub gap: f64,
+    /// share of the volumes moved to the all-or-nothing or logit assignment
+    pub step: f64,
+}
+
+#[derive(Debug, Clone, Default, serde::Serialize, serde::Deserialize)]
+pub struct Assignment {
+    /// vehicles per hour on every link, by graph edge index
+    pub volumes: Vec<f64>,
+    /// free flow cost of every link, by graph edge index
+    pub free: Vec<f64>,
+    /// congested cost of every link, by graph edge index
+    pub times: Vec<f64>,
+    /// paths of every plan as the graph edge indices travelled and their share of its flow,
+    /// empty when the plan could not be routed
+    pub paths: Vec<Vec<(Vec<u32>, f64)>>,
+    /// `pair.id` of every plan alongside `paths`, left empty by the assignment for the caller
+    pub ids: Vec<i32>,
+    pub iterations: Vec<Iteration>,
+}
+
+impl Assignment {
+    /// The path of every plan with the largest share, empty when it could not be routed.
+    pub fn main_paths(&self) -> Vec<Vec<u32>> {
+        self.paths
+            .iter()
+            .map(|paths| {
+                paths
+                    .iter()
+                    .max_by(|a, b| a.1.total_cmp(&b.1))
+                    .map(|(links, _)| links.clone())
+                    .unwrap_or_default()
+            })
+            .collect()
+    }
+}
+
+/// BPR cost of a link with free flow cost `free`.
+pub fn bpr(free: f64, volume: f64, capacity: f64, options: &Options) -> f64 {
+    if capacity <= 0.0 {
+        return free;
+    }
+    free * (1.0 + options.alpha * (volume / capacity).powf(options.beta))
+}
+
+/// Assign the plans given the free flow cost of every link, `route` giving the cheapest links of
+/// every plan under the costs it is passed plus the penalties of `turns`.
+///
+/// Panics unless alpha and beta are zero or more, so congested costs never fall below free flow.
+pub fn assign(
+    graph: &crate::Graph,
+    free: &[f64],
+    turns: &crate::turn::Turns,
+    options: &Options,
+    mut route: impl FnMut(&[f64]) -> Vec<Option<Vec<petgraph::graph::EdgeIndex>>>,
+) -> Assignment {
+    assert!(options.alpha >= 0.0, "alpha must be zero or more");
+    assert!(options.beta >= 0.0, "beta must be zero or more");
+
+    let costs = |volumes: &[f64]| times(graph, free, volumes, options);
+
+    let routes = route(free);
+    let mut volumes = load(graph, &routes, options.flow);
+    let mut paths = routes
+        .iter()
+        .map(|links| match links {
+            Some(inner) => vec![(indices(inner), 1.0)],
+            None => vec![],
+        })
+        .collect::<Vec<_>>();
+    let mut iterations = vec![];
+
+    if options.method != Method::AllOrNothing {
+        for k in 1..=options.iterations {
+            let times = costs(&volumes);
+            let routes = route(&times);
+            let target = load(graph, &routes, options.flow);
+
+            // flow weighted path costs and penalties, of the current paths and of the cheapest
+            let (mut total, mut excess, mut penalties) = (0.0, 0.0, 0.0);
+            for (paths, links) in paths.iter().zip(&routes) {
+                let links = match links {
+                    Some(inner) => indices(inner),
+                    None => continue,
+                };
+                let cheapest = cost(turns, &times, &links);
+                penalties += options.flow * penalty(turns, &links);
+
+                for (path, share) in paths {
+                    let c = cost(turns, &times, path);
+                    total += share * options.flow * c;
+                    excess += share * options.flow * (c - cheapest);
+                    penalties -= share * options.flow * penalty(turns, path);
+                }
+            }
+            let gap = match total > 0.0 {
+                true => excess / total,
+                false => 0.0,
+            };
+
+            let moved = volumes
+                .iter()
+                .zip(&target)
+                .any(|(v, y)| (y - v).abs() > 1e-9 * options.flow);
+            if gap < options.gap && (gap > 0.0 || !moved) {
+                iterations.push(Iteration { gap, step: 0.0 });
+                break;
+            }
+
+            let step = match options.method {
+                Method::Msa => 1.0 / (k as f64 + 1.0),
+                _ => line_search(&volumes, &target, penalties, |volumes| costs(volumes)),
+            };
+            iterations.push(Iteration { gap, step });
+
+            for (v, y) in volumes.iter_mut().zip(&target) {
+                *v += step * (y - *v);
+            }
+
+            for (paths, links) in paths.iter_mut().zip(&routes) {
+                let links = match links {
+                    Some(inner) => indices(inner),
+                    None => continue,
+                };
+                for path in paths.iter_mut() {
+                    path.1 *= 1.0 - step;
+                }
+                match paths.iter_mut().find(|path| path.0 == links) {
+                    Some(path) => path.1 += step,
+                    None => paths.push((links, step)),
+                }
+                paths.retain(|path| path.1 > 0.0);
+            }
+        }
+    }
+
+    Assignment {
+        times: costs(&volumes),
+        free: free.to_vec(),
+        volumes,
+        paths,
+        ids: vec![],
+        iterations,
+    }
+}
+
+/// Assign the plans to their alternative `routes`, see the module documentation.
+pub fn stochastic(
+    graph: &crate::Graph,
+    free: &[f64],
+    options: &Options,
+    routes: &[Vec<crate::alternative::Route>],
+) -> Assignment {
+    let split = |costs: &[f64]| {
+        routes
+            .iter()
+            .map(|routes| logit(routes, costs, options.dispersion))
+            .collect::<Vec<_>>()
+    };
+    let load = |shares: &[Vec<f64>]| {
+        let mut volumes = vec![0.0; graph.edge_count()];
+        for (routes, shares) in routes.iter().zip(shares) {
+            for (route, share) in routes.iter().zip(shares) {
+                for link in &route.links {
+                    volumes[link.index()] += share * options.flow;
+                }
+            }
+        }
+        volumes
+    };
+
+    let mut shares = split(free);
+    let mut volumes = load(&shares);
+    let mut iterations = vec![];
+
+    for k in 1..=options.iterations {
+        let target_shares = split(&times(graph, free, &volumes, options));
+        let target = load(&target_shares);
+
+        let total = volumes.iter().sum::<f64>();
+        let gap = match total > 0.0 {
+            true => {
+                let change = volumes
+                    .iter()
+                    .zip(&target)
+                    .map(|(v, y)| (y - v).abs())
+                    .sum::<f64>();
+                change / total
+            }
+            false => 0.0,
+        };
+
+        if gap < options.gap {
+            iterations.push(Iteration { gap, step: 0.0 });
+            break;
+        }
+
+        let step = 1.0 / (k as f64 + 1.0);
+        iterations.push(Iteration { gap, step });
+
+        for (v, y) in volumes.iter_mut().zip(&target) {
+            *v += step * (y - *v);
+        }
+        for (shares, target) in shares.iter_mut().zip(&target_shares) {
+            for (share, y) in shares.iter_mut().zip(target) {
+                *share += step * (y - *share);
+            }
+        }
+    }
+
+    Assignment {
+        times: times(graph, free, &volumes, options),
+        free: free.to_vec(),
+        volumes,
+        paths: routes
+            .iter()
+            .zip(&shares)
+            .map(|(routes, shares)| {
+                routes
+                    .iter()
+                    .zip(shares)
+                    .map(|(route, share)| (indices(&route.links), *share))
+                    .collect()
+            })
+            .collect(),
+        ids: vec![],
+        iterations,
+    }
+}
+
+/// Share of every route under the link `costs`.
+fn logit(routes: &[crate::alternative::Route], costs: &[f64], dispersion: f64) -> Vec<f64> {
+    let costs = routes
+        .iter()
+        .map(|route| route.cost_with(costs))
+        .collect::<Vec<_>>();
+
+    // relative to the cheapest route so the exponentials do not underflow
+    let cheapest = costs.iter().copied().fold(f64::INFINITY, f64::min);
+    let weights = costs
+        .iter()
+        .map(|cost| (-dispersion * (cost - cheapest)).exp())
+        .collect::<Vec<_>>();
+    let total = weights.iter().sum::<f64>();
+
+    weights.iter().map(|weight| weight / total).collect()
+}
+
+/// BPR cost of every link under `volumes`.
+fn times(graph: &crate::Graph, free: &[f64], volumes: &[f64], options: &Options) -> Vec<f64> {
+    graph
+        .edge_weights()
+        .zip(volumes)
+        .enumerate()
+        .map(|(i, (link, v))| bpr(free[i], *v, link.capacity, options))
+        .collect()
+}
+
+fn indices(links: &[petgraph::graph::EdgeIndex]) -> Vec<u32> {
+    links.iter().map(|link| link.index() as u32).collect()
+}
+
+/// Penalties of the movements along a path.
+fn penalty(turns: &crate::turn::Turns, links: &[u32]) -> f64 {
+    links
+        .windows(2)
+        .filter_map(|pair| {
+            let a = petgraph::graph::EdgeIndex::new(pair[0] as usize);
+            let b = petgraph::graph::EdgeIndex::new(pair[1] as usize);
+            turns.penalty(a, b)
+        })
+        .sum()
+}
+
+/// Cost of a path under the link `times`, with the penalties of its movements.
+fn cost(turns: &crate::turn::Turns, times: &[f64], links: &[u32]) -> f64 {
+    let times = links.iter().map(|link| times[*link as usize]).sum::<f64>();
+    times + penalty(turns, links)
+}
+
+/// Volumes on every link with every routed plan on its links.
+fn load(
+    graph: &crate::Graph,
+    routes: &[Option<Vec<petgraph::graph::EdgeIndex>>],
+    flow: f64,
+) -> Vec<f64> {
+    let mut volumes = vec![0.0; graph.edge_count()];
+    for links in routes.iter().flatten() {
+        for link in links {
+            volumes[link.index()] += flow;
+        }
+    }
+    volumes
+}
+
+/// Step towards `target` minimising the Beckmann objective plus the turn penalties, by bisection on
+/// its derivative. `penalties` is the change of the flow weighted penalties over the whole step.
+fn line_search(
+    volumes: &[f64],
+    target: &[f64],
+    penalties: f64,
+    costs: impl Fn(&[f64]) -> Vec<f64>,
+) -> f64 {
+    let derivative = |step: f64| {
+        let moved = volumes
+            .iter()
+            .zip(target)
+            .map(|(v, y)| v + step * (y - v))
+            .collect::<Vec<_>>();
+        costs(&moved)
+            .iter()
+            .enumerate()
+            .map(|(i, t)| t * (target[i] - volumes[i]))
+            .sum::<f64>()
+            + penalties
+    };
+
+    if derivative(1.0) <= 0.0 {
+        return 1.0;
+    }
+
+    let (mut low, mut high) = (0.0, 1.0);
+    for _ in 0..30 {
+        let middle = (low + high) / 2.0;
+        match derivative(middle) > 0.0 {
+            true => high = middle,
+            false => low = middle,
+        }
+    }
+
+    (low + high) / 2.0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use petgraph::graph::{EdgeIndex, NodeIndex};
+
+    /// `O -> A -> D` with a left turn at `A` and `O -> B -> D` straight on, every link 10 s at free
+    /// flow. The links are `O -> A`, `A -> D`, `O -> B` and `B -> D` in that order.
+    fn routes() -> (crate::Graph, crate::turn::Turns) {
+        let network = crate::testing::network(
+            &[(0.0, 0.0), (0.001, 0.0), (0.001, 0.001), (0.0005, 0.0005)],
+            &[(0, 1), (1, 2), (0, 3), (3, 2)],
+        );
+        let graph = crate::testing::graph(&network, crate::Oneway::Forward, 10.0);
+        let turns = crate::turn::Turns {
+            next: vec![vec![(1, 10.0)], vec![], vec![(3, 0.0)], vec![]],
+        };
+        (graph, turns)
+    }
+
+    #[test]
+    fn takes_turn_penalties_into_the_equilibrium() {
+        let (graph, turns) = routes();
+        let options = Options {
+            alpha: 1.0,
+            beta: 1.0,
+            ..Default::default()
+        };
+
+        let route = |costs: &[f64]| {
+            let path = turns.astar(
+                &graph,
+                NodeIndex::new(0),
+                NodeIndex::new(2),
+                |edge, _| costs[edge.index()],
+                |_| 0.0,
+            );
+            vec![path.map(|(_, links)| links); 20]
+        };
+        let assignment = assign(&graph, &[10.0; 4], &turns, &options, route);
+
+        // 20 + 2 vA + 10 = 20 + 2 vB with vA + vB = 20, not the even split without the penalty
+        assert!((assignment.volumes[0] - 7.5).abs() < 0.01);
+        assert!((assignment.volumes[2] - 12.5).abs() < 0.01);
+
+        let last = assignment.iterations.last().unwrap();
+        assert!(last.gap >= 0.0 && last.gap < options.gap);
+    }
+
+    /// The two routes of `routes()` for `plans` plans.
+    fn alternatives(plans: usize) -> (crate::Graph, Vec<Vec<crate::alternative::Route>>) {
+        let (graph, turns) = routes();
+        let route = |links: [usize; 2]| {
+            let links = links.iter().map(|l| EdgeIndex::new(*l)).collect();
+            crate::alternative::Route::new(&turns, &graph, NodeIndex::new(0), links, |_, _| 10.0)
+        };
+        let alternatives = vec![route([0, 1]), route([2, 3])];
+        (graph, vec![alternatives; plans])
+    }
+
+    #[test]
+    fn splits_by_logit_of_the_route_costs() {
+        let (graph, routes) = alternatives(2);
+        let options = Options {
+            alpha: 0.0,
+            ..Default::default()
+        };
+        let assignment = stochastic(&graph, &[10.0; 4], &options, &routes);
+
+        // 30 against 20, the left turn included
+        let share = 1.0 / (1.0 + (options.dispersion * 10.0).exp());
+        for paths in &assignment.paths {
+            assert_eq!(paths.len(), 2);
+            assert!((paths[0].1 - share).abs() < 1e-9);
+            assert!((paths[0].1 + paths[1].1 - 1.0).abs() < 1e-9);
+        }
+        assert!((assignment.volumes[0] - 2.0 * share).abs() < 1e-9);
+        assert!((assignment.volumes[2] - 2.0 * (1.0 - share)).abs() < 1e-9);
+        assert_eq!(assignment.iterations.len(), 1);
+    }
+
+    #[test]
+    fn moves_flow_off_the_congested_route() {
+        let (graph, routes) = alternatives(20);
+        let options = Options {
+            alpha: 1.0,
+            beta: 1.0,
+            ..Default::default()
+        };
+        let assignment = stochastic(&graph, &[10.0; 4], &options, &routes);
+
+        let free = 1.0 / (1.0 + (options.dispersion * 10.0).exp());
+        let (a, b) = (assignment.volumes[0], assignment.volumes[2]);
+        assert!((a + b - 20.0).abs() < 1e-9);
+        assert!(a > 20.0 * free);
+        // the logit split leaves more flow on the route which stays the cheaper one
+        assert!(b > a);
+        let (ta, tb) = (
+            assignment.times[0] + assignment.times[1],
+            assignment.times[2] + assignment.times[3],
+        );
+        assert!(tb < ta + 10.0);
+    }
+}
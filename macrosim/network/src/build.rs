@@ -0,0 +1,427 @@
+//! Road network topology built directly from road centre lines.
+//!
+//! Every vertex becomes a node and every segment an edge, like `graph.sql` did, with three
+//! additions: lines crossing on the same level are split at the crossing, a line end within the
+//! snapping tolerance of another line is connected to it, and line ends close to a node are merged
+//! into it. The work is done in a local equirectangular projection so tolerances are in metres.
+
+use crate::projection::{coord, distance_2, Point, Projection};
+use crate::{Edge, Network};
+
+#[derive(Debug, Clone)]
+pub struct Line {
+    /// id of the line, kept on its edges
+    pub source: i32,
+    /// grade separation level (`lvOrder`), crossing lines only meet on the same level
+    pub level: i32,
+    pub geometry: geo::LineString<f64>,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct Options {
+    /// distance in metres within which a line end is connected to another line or node
+    pub snap: f64,
+    /// split lines crossing on the same level
+    pub split: bool,
+}
+
+impl Default for Options {
+    fn default() -> Self {
+        Self {
+            snap: 0.5,
+            split: true,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Stats {
+    /// segment pairs split at their crossing
+    pub crossings: usize,
+    /// line ends moved onto another line or node
+    pub snapped: usize,
+}
+
+impl Line {
+    /// Lines of a GeoJSON feature collection, with `lvOrder` as the level and the feature index as
+    /// the source.
+    pub fn from_geojson(text: &str) -> Result<Vec<Line>, Box<geojson::Error>> {
+        let collection = geojson::FeatureCollection::try_from(text.parse::<geojson::GeoJson>()?)?;
+
+        let mut lines = vec![];
+        for (i, feature) in collection.features.into_iter().enumerate() {
+            let level = feature
+                .property("lvOrder")
+                .and_then(|value| match value {
+                    geojson::JsonValue::String(inner) => inner.parse().ok(),
+                    inner => inner.as_i64(),
+                })
+                .unwrap_or(0) as i32;
+
+            let geometry = match feature.geometry {
+                Some(inner) => inner,
+                None => continue,
+            };
+
+            let parts = match geo::Geometry::<f64>::try_from(geometry.value)? {
+                geo::Geometry::LineString(line) => vec![line],
+                geo::Geometry::MultiLineString(lines) => lines.0,
+                _ => continue,
+            };
+
+            for part in parts {
+                lines.push(Line {
+                    source: i as i32,
+                    level,
+                    geometry: part,
+                });
+            }
+        }
+
+        Ok(lines)
+    }
+}
+
+type Segment = rstar::primitives::GeomWithData<rstar::primitives::Line<Point>, (usize, usize)>;
+
+pub fn build(lines: &[Line], options: &Options) -> (Network, Stats) {
+    let projection = Projection::around(
+        lines
+            .iter()
+            .flat_map(|line| line.geometry.0.iter().map(|c| c.y)),
+    );
+    let mut stats = Stats::default();
+
+    let mut coords = lines
+        .iter()
+        .map(|line| {
+            let mut coords = line
+                .geometry
+                .0
+                .iter()
+                .map(|c| projection.forward(c))
+                .collect::<Vec<_>>();
+            coords.dedup();
+            coords
+        })
+        .collect::<Vec<_>>();
+
+    let segments = coords
+        .iter()
+        .enumerate()
+        .flat_map(|(i, coords)| {
+            coords.windows(2).enumerate().map(move |(j, pair)| {
+                Segment::new(rstar::primitives::Line::new(pair[0], pair[1]), (i, j))
+            })
+        })
+        .collect::<Vec<_>>();
+    let tree = rstar::RTree::bulk_load(segments);
+
+    // points where each segment is cut
+    let mut cuts = std::collections::HashMap::<(usize, usize), Vec<Point>>::new();
+
+    if options.split {
+        for a in tree.iter() {
+            let envelope = rstar::RTreeObject::envelope(a);
+            for b in tree.locate_in_envelope_intersecting(&envelope) {
+                let ((i, j), (k, l)) = (a.data, b.data);
+                if (k, l) <= (i, j) || lines[i].level != lines[k].level {
+                    continue;
+                }
+                if i == k && (l == j + 1 || j == l + 1) {
+                    continue;
+                }
+
+                let p = geo::Line::new(coord(&a.geom().from), coord(&a.geom().to));
+                let q = geo::Line::new(coord(&b.geom().from), coord(&b.geom().to));
+
+                let x = match geo::line_intersection::line_intersection(p, q) {
+                    Some(geo::line_intersection::LineIntersection::SinglePoint {
+                        intersection,
+                        ..
+                    }) => [intersection.x, intersection.y],
+                    _ => continue,
+                };
+
+                let mut split = false;
+                for (segment, line) in [(a, (i, j)), (b, (k, l))] {
+                    if x != segment.geom().from && x != segment.geom().to {
+                        cuts.entry(line).or_default().push(x);
+                        split = true;
+                    }
+                }
+                if split {
+                    stats.crossings += 1;
+                }
+            }
+        }
+    }
+
+    // dangling ends close to another line
+    for i in 0..coords.len() {
+        if coords[i].len() < 2 {
+            continue;
+        }
+
+        for end in [0, coords[i].len() - 1] {
+            let p = coords[i][end];
+
+            let nearest = tree
+                .locate_within_distance(p, options.snap * options.snap)
+                .filter(|s| s.data.0 != i && lines[s.data.0].level == lines[i].level)
+                .map(|s| (closest(s.geom(), &p), s))
+                .min_by(|a, b| distance_2(&a.0, &p).total_cmp(&distance_2(&b.0, &p)));
+
+            let (q, segment) = match nearest {
+                Some(inner) => inner,
+                None => continue,
+            };
+
+            if q != segment.geom().from && q != segment.geom().to {
+                cuts.entry(segment.data).or_default().push(q);
+            }
+            if q != p {
+                coords[i][end] = q;
+                stats.snapped += 1;
+            }
+        }
+    }
+
+    // lines with the cut points inserted
+    for (i, coords) in coords.iter_mut().enumerate() {
+        let mut cut = vec![];
+
+        for (j, pair) in coords.windows(2).enumerate() {
+            cut.push(pair[0]);
+
+            if let Some(points) = cuts.get_mut(&(i, j)) {
+                points.sort_by(|a, b| distance_2(a, &pair[0]).total_cmp(&distance_2(b, &pair[0])));
+                cut.extend(points.iter().copied());
+            }
+        }
+
+        if let Some(last) = coords.last() {
+            cut.push(*last);
+        }
+
+        cut.dedup();
+        *coords = cut;
+    }
+
+    // nodes, shared by vertices at the same millimetre
+    let mut index = std::collections::HashMap::<(i64, i64), usize>::new();
+    let mut positions = vec![];
+    let vertices = coords
+        .iter()
+        .map(|coords| {
+            coords
+                .iter()
+                .map(|p| {
+                    let key = ((p[0] * 1e3).round() as i64, (p[1] * 1e3).round() as i64);
+                    *index.entry(key).or_insert_with(|| {
+                        positions.push(*p);
+                        positions.len() - 1
+                    })
+                })
+                .collect::<Vec<_>>()
+        })
+        .collect::<Vec<_>>();
+
+    // line ends merged into nodes within the tolerance
+    let mut parents = (0..positions.len()).collect::<Vec<_>>();
+    let node_tree = rstar::RTree::bulk_load(
+        positions
+            .iter()
+            .enumerate()
+            .map(|(n, p)| rstar::primitives::GeomWithData::new(*p, n))
+            .collect(),
+    );
+
+    for vertices in &vertices {
+        for &n in [vertices.first(), vertices.last()].into_iter().flatten() {
+            for other in node_tree.locate_within_distance(positions[n], options.snap * options.snap)
+            {
+                let (a, b) = (find(&mut parents, n), find(&mut parents, other.data));
+                if a != b {
+                    parents[a.max(b)] = a.min(b);
+                    stats.snapped += 1;
+                }
+            }
+        }
+    }
+
+    let mut compact = vec![usize::MAX; positions.len()];
+    let mut nodes = vec![];
+    for n in 0..positions.len() {
+        let root = find(&mut parents, n);
+        if compact[root] == usize::MAX {
+            compact[root] = nodes.len();
+            nodes.push(projection.inverse(&positions[root]));
+        }
+        compact[n] = compact[root];
+    }
+
+    let mut edges = vec![];
+    for (i, vertices) in vertices.iter().enumerate() {
+        for pair in vertices.windows(2) {
+            let (n1, n2) = (compact[pair[0]], compact[pair[1]]);
+            if n1 == n2 {
+                continue;
+            }
+
+            let p1 = geo::Point::from(nodes[n1]);
+            let p2 = geo::Point::from(nodes[n2]);
+
+            edges.push(Edge {
+                n1,
+                n2,
+                distance: geo::GeodesicDistance::geodesic_distance(&p1, &p2),
+                source: lines[i].source,
+            });
+        }
+    }
+
+    (Network { nodes, edges }, stats)
+}
+
+/// Closest point on the segment.
+fn closest(segment: &rstar::primitives::Line<Point>, p: &Point) -> Point {
+    let (a, b) = (segment.from, segment.to);
+    let d = [b[0] - a[0], b[1] - a[1]];
+    let length_2 = d[0] * d[0] + d[1] * d[1];
+    if length_2 == 0.0 {
+        return a;
+    }
+
+    let t = ((p[0] - a[0]) * d[0] + (p[1] - a[1]) * d[1]) / length_2;
+    if t <= 0.0 {
+        a
+    } else if t >= 1.0 {
+        b
+    } else {
+        [a[0] + t * d[0], a[1] + t * d[1]]
+    }
+}
+
+fn find(parents: &mut [usize], n: usize) -> usize {
+    let mut root = n;
+    while parents[root] != root {
+        root = parents[root];
+    }
+
+    let mut n = n;
+    while parents[n] != root {
+        let next = parents[n];
+        parents[n] = root;
+        n = next;
+    }
+
+    root
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn fixture() -> Vec<Line> {
+        Line::from_geojson(include_str!("../fixtures/build.geojson")).unwrap()
+    }
+
+    /// Node closest to `(lon, lat)` and its distance in metres.
+    fn nearest(network: &Network, lon: f64, lat: f64) -> (usize, f64) {
+        let p = geo::Point::new(lon, lat);
+        network
+            .nodes
+            .iter()
+            .map(|n| geo::GeodesicDistance::geodesic_distance(&p, &geo::Point::from(*n)))
+            .enumerate()
+            .min_by(|a, b| a.1.total_cmp(&b.1))
+            .unwrap()
+    }
+
+    fn degree(network: &Network, n: usize) -> usize {
+        network
+            .edges
+            .iter()
+            .filter(|e| e.n1 == n || e.n2 == n)
+            .count()
+    }
+
+    #[test]
+    fn reads_levels_and_sources() {
+        let lines = fixture();
+
+        assert_eq!(lines.len(), 5);
+        assert_eq!(
+            lines.iter().map(|l| l.level).collect::<Vec<_>>(),
+            [0, 0, 1, 0, 0]
+        );
+        assert_eq!(
+            lines.iter().map(|l| l.source).collect::<Vec<_>>(),
+            [0, 1, 2, 3, 4]
+        );
+    }
+
+    #[test]
+    fn splits_crossings_on_the_same_level() {
+        let (network, stats) = build(&fixture(), &Options::default());
+
+        assert_eq!(stats.crossings, 1);
+        let (n, d) = nearest(&network, 137.101, 36.700);
+        assert!(d < 0.01);
+        assert_eq!(degree(&network, n), 4);
+    }
+
+    #[test]
+    fn keeps_levels_apart() {
+        let (network, _) = build(&fixture(), &Options::default());
+
+        // the bridge meets the main road nowhere
+        let (_, d) = nearest(&network, 137.1015, 36.700);
+        assert!(d > 10.0);
+        assert_eq!(network.edges.iter().filter(|e| e.source == 2).count(), 1);
+    }
+
+    #[test]
+    fn snaps_ends_within_the_tolerance() {
+        let (network, stats) = build(&fixture(), &Options::default());
+
+        assert_eq!(stats.snapped, 1);
+        let (n, d) = nearest(&network, 137.1005, 36.700);
+        assert!(d < 0.01);
+        assert_eq!(degree(&network, n), 3);
+
+        // 2 m is beyond the tolerance, the end stays a dead end
+        let (n, d) = nearest(&network, 137.1018, 36.700018);
+        assert!(d < 0.01);
+        assert_eq!(degree(&network, n), 1);
+    }
+
+    #[test]
+    fn measures_geodesic_lengths() {
+        let (network, _) = build(&fixture(), &Options::default());
+
+        let length = network
+            .edges
+            .iter()
+            .filter(|e| e.source == 0)
+            .map(|e| e.distance)
+            .sum::<f64>();
+        let expected = geo::GeodesicDistance::geodesic_distance(
+            &geo::Point::new(137.100, 36.700),
+            &geo::Point::new(137.102, 36.700),
+        );
+
+        assert_eq!(network.edges.iter().filter(|e| e.source == 0).count(), 3);
+        assert!((length - expected).abs() < 1e-3);
+        assert!((178.0..180.0).contains(&length));
+    }
+
+    #[test]
+    fn counts_nodes_and_edges() {
+        let (network, _) = build(&fixture(), &Options::default());
+
+        assert_eq!(network.nodes.len(), 11);
+        assert_eq!(network.edges.len(), 8);
+    }
+}
@@ -0,0 +1,23 @@
+//! Typical speed and capacity of the rdcl road categories (`rdCtg`).
+
+/// Free flow speed in metres per second.
+pub fn speed(rdctg: Option<&str>) -> f64 {
+    let kmh = match rdctg {
+        Some("高速自動車国道等") => 80.0,
+        Some("国道") => 50.0,
+        Some("都道府県道") => 40.0,
+        _ => 30.0,
+    };
+
+    kmh / 3.6
+}
+
+/// Vehicles per hour and lane.
+pub fn lane_capacity(rdctg: Option<&str>) -> f64 {
+    match rdctg {
+        Some("高速自動車国道等") => 2200.0,
+        Some("国道") => 1500.0,
+        Some("都道府県道") => 1200.0,
+        _ => 1000.0,
+    }
+}
@@ -0,0 +1,239 @@
+//! Contraction of chains of degree-2 nodes.
+//!
+//! Every vertex of a road polyline is a node of the built network, so most nodes only join the two
+//! segments of one road. Such chains are replaced by a single edge between the nodes at their ends,
+//! keeping the polyline and the summed length, and the segments each edge is made of.
+
+use crate::{Edge, Network};
+
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Options {
+    /// also contract nodes joining edges of different source lines
+    pub across_sources: bool,
+}
+
+#[derive(Debug, Clone)]
+pub struct Contracted {
+    pub network: Network,
+    /// index of every kept node in the original network
+    pub nodes: Vec<usize>,
+    /// original edges of every edge, in order from `n1` to `n2`
+    pub segments: Vec<Vec<usize>>,
+    /// `(lon, lat)` polyline of every edge from `n1` to `n2`
+    pub geometry: Vec<Vec<(f64, f64)>>,
+}
+
+pub fn contract(network: &Network, options: &Options) -> Contracted {
+    let mut incident = vec![vec![]; network.nodes.len()];
+    for (i, edge) in network.edges.iter().enumerate() {
+        incident[edge.n1].push(i);
+        incident[edge.n2].push(i);
+    }
+
+    let mut kept = incident
+        .iter()
+        .map(|edges| match edges.as_slice() {
+            [a, b] if a != b => {
+                let (a, b) = (&network.edges[*a], &network.edges[*b]);
+                !options.across_sources && a.source != b.source
+            }
+            _ => true,
+        })
+        .collect::<Vec<_>>();
+
+    let mut visited = vec![false; network.edges.len()];
+    let mut chains = vec![];
+
+    let mut walk = |start: usize, first: usize, kept: &[bool], visited: &mut [bool]| {
+        let mut chain = vec![];
+        let (mut current, mut edge) = (start, first);
+
+        loop {
+            visited[edge] = true;
+            chain.push(edge);
+
+            let next = other(&network.edges[edge], current);
+            if kept[next] {
+                chains.push((start, next, chain));
+                break;
+            }
+
+            edge = match incident[next].as_slice() {
+                [a, b] if *a == edge => *b,
+                [a, _] => *a,
+                _ => unreachable!(),
+            };
+            current = next;
+        }
+    };
+
+    for n in 0..network.nodes.len() {
+        if !kept[n] {
+            continue;
+        }
+        for &edge in &incident[n] {
+            if !visited[edge] {
+                walk(n, edge, &kept, &mut visited);
+            }
+        }
+    }
+
+    // rings of degree-2 nodes only keep one of them
+    for edge in 0..network.edges.len() {
+        if !visited[edge] {
+            let n = network.edges[edge].n1;
+            kept[n] = true;
+            walk(n, edge, &kept, &mut visited);
+        }
+    }
+
+    let mut compact = vec![usize::MAX; network.nodes.len()];
+    let mut nodes = vec![];
+    for (n, kept) in kept.iter().enumerate() {
+        if *kept {
+            compact[n] = nodes.len();
+            nodes.push(n);
+        }
+    }
+
+    let mut contracted = Contracted {
+        network: Network {
+            nodes: nodes.iter().map(|n| network.nodes[*n]).collect(),
+            edges: vec![],
+        },
+        nodes,
+        segments: vec![],
+        geometry: vec![],
+    };
+
+    for (start, end, mut chain) in chains {
+        // edges run the way their first segment does, see `Edge`
+        let (start, end) = match network.edges[chain[0]].n1 == start {
+            true => (start, end),
+            false => {
+                chain.reverse();
+                (end, start)
+            }
+        };
+
+        let mut geometry = vec![network.nodes[start]];
+        let mut current = start;
+        for &edge in &chain {
+            current = other(&network.edges[edge], current);
+            geometry.push(network.nodes[current]);
+        }
+
+        contracted.network.edges.push(Edge {
+            n1: compact[start],
+            n2: compact[end],
+            distance: chain.iter().map(|e| network.edges[*e].distance).sum(),
+            source: network.edges[chain[0]].source,
+        });
+        contracted.segments.push(chain);
+        contracted.geometry.push(geometry);
+    }
+
+    contracted
+}
+
+fn other(edge: &Edge, n: usize) -> usize {
+    if edge.n1 == n {
+        edge.n2
+    } else {
+        edge.n1
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::testing::network;
+
+    fn nodes(count: usize) -> Vec<(f64, f64)> {
+        (0..count).map(|i| (i as f64, 0.0)).collect()
+    }
+
+    #[test]
+    fn contracts_a_chain_into_one_edge() {
+        let network = network(&nodes(4), &[(0, 1), (1, 2), (2, 3)]);
+        let contracted = contract(&network, &Options::default());
+
+        assert_eq!(contracted.nodes, vec![0, 3]);
+        assert_eq!(contracted.network.edges.len(), 1);
+        let edge = &contracted.network.edges[0];
+        assert_eq!((edge.n1, edge.n2), (0, 1));
+        assert_eq!(edge.distance, 300.0);
+        assert_eq!(contracted.segments, vec![vec![0, 1, 2]]);
+        assert_eq!(contracted.geometry[0], nodes(4));
+    }
+
+    #[test]
+    fn keeps_the_direction_of_the_first_segment() {
+        // the chain is found from node 0, against its first segment
+        let network = network(&nodes(3), &[(1, 0), (1, 2)]);
+        let contracted = contract(&network, &Options::default());
+
+        assert_eq!(contracted.nodes, vec![0, 2]);
+        let edge = &contracted.network.edges[0];
+        assert_eq!((edge.n1, edge.n2), (1, 0));
+        assert_eq!(contracted.segments, vec![vec![1, 0]]);
+        assert_eq!(
+            contracted.geometry[0],
+            vec![(2.0, 0.0), (1.0, 0.0), (0.0, 0.0)]
+        );
+    }
+
+    #[test]
+    fn keeps_one_node_of_a_ring() {
+        let network = network(&nodes(3), &[(0, 1), (1, 2), (2, 0)]);
+        let contracted = contract(&network, &Options::default());
+
+        assert_eq!(contracted.nodes, vec![0]);
+        assert_eq!(contracted.network.edges.len(), 1);
+        let edge = &contracted.network.edges[0];
+        assert_eq!((edge.n1, edge.n2), (0, 0));
+        assert_eq!(contracted.segments[0].len(), 3);
+        assert_eq!(
+            contracted.geometry[0].first(),
+            contracted.geometry[0].last()
+        );
+    }
+
+    #[test]
+    fn stops_between_sources_unless_asked() {
+        let mut network = network(&nodes(3), &[(0, 1), (1, 2)]);
+        network.edges[1].source = 1;
+
+        let contracted = contract(&network, &Options::default());
+        assert_eq!(contracted.nodes, vec![0, 1, 2]);
+        assert_eq!(contracted.segments, vec![vec![0], vec![1]]);
+
+        let options = Options {
+            across_sources: true,
+        };
+        let contracted = contract(&network, &options);
+        assert_eq!(contracted.nodes, vec![0, 2]);
+        assert_eq!(contracted.segments, vec![vec![0, 1]]);
+        assert_eq!(contracted.network.edges[0].source, 0);
+    }
+
+    #[test]
+    fn maps_every_segment_to_one_edge() {
+        // a cross of two chains meeting at node 2
+        let network = network(&nodes(7), &[(0, 1), (1, 2), (2, 3), (3, 4), (5, 2), (2, 6)]);
+        let contracted = contract(&network, &Options::default());
+
+        let mut segments = contracted.segments.concat();
+        segments.sort();
+        assert_eq!(segments, (0..network.edges.len()).collect::<Vec<_>>());
+
+        for (i, segments) in contracted.segments.iter().enumerate() {
+            let distance = segments
+                .iter()
+                .map(|s| network.edges[*s].distance)
+                .sum::<f64>();
+            assert_eq!(contracted.network.edges[i].distance, distance);
+        }
+    }
+}
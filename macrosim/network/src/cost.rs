@@ -0,0 +1,108 @@
+//! Routing cost of a link.
+
+/// How the cost of a link is computed.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Strategy {
+    /// free flow travel time in seconds
+    #[default]
+    Time,
+    /// length divided by the lane count, the original cost of `macrosim/graph`
+    Lanes,
+}
+
+impl std::str::FromStr for Strategy {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "time" => Ok(Strategy::Time),
+            "lanes" => Ok(Strategy::Lanes),
+            _ => Err(format!(
+                "unknown cost strategy {}, expected time or lanes",
+                s
+            )),
+        }
+    }
+}
+
+impl std::fmt::Display for Strategy {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Strategy::Time => write!(f, "time"),
+            Strategy::Lanes => write!(f, "lanes"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct Model {
+    pub strategy: Strategy,
+    /// share of the speed limit driven on a road with a single lane per direction
+    pub single_lane: f64,
+}
+
+impl Default for Model {
+    fn default() -> Self {
+        Self {
+            strategy: Strategy::Time,
+            single_lane: 0.8,
+        }
+    }
+}
+
+impl Model {
+    /// Free flow speed of the link in metres per second.
+    pub fn speed(&self, link: &crate::Link) -> f64 {
+        match link.lanes {
+            0 | 1 => link.speed * self.single_lane,
+            _ => link.speed,
+        }
+    }
+
+    pub fn cost(&self, link: &crate::Link) -> f64 {
+        match self.strategy {
+            Strategy::Time => link.distance / self.speed(link),
+            Strategy::Lanes => link.distance / link.lanes as f64,
+        }
+    }
+}
+
+/// Lower bound of the cost to a node, for A*.
+///
+/// The haversine distance is divided by the most distance any link covers per unit of cost, so
+/// it never overestimates whichever strategy is used. Edge lengths are geodesic, on the
+/// ellipsoid, which can be a little shorter than on the haversine sphere, hence the margin.
+#[derive(Debug, Clone, Copy)]
+pub struct Heuristic {
+    /// metres per unit of cost
+    rate: f64,
+}
+
+impl Heuristic {
+    const MARGIN: f64 = 0.995;
+
+    pub fn new(graph: &crate::Graph, model: &Model) -> Self {
+        let rate = graph
+            .edge_weights()
+            .map(|link| link.distance / model.cost(link))
+            .filter(|rate| rate.is_finite())
+            .fold(0.0, f64::max);
+
+        Self { rate }
+    }
+
+    pub fn estimate(
+        &self,
+        graph: &crate::Graph,
+        n: petgraph::graph::NodeIndex,
+        target: petgraph::graph::NodeIndex,
+    ) -> f64 {
+        if self.rate == 0.0 {
+            return 0.0;
+        }
+
+        let p1 = geo::Point::from(graph[n]);
+        let p2 = geo::Point::from(graph[target]);
+        geo::HaversineDistance::haversine_distance(&p1, &p2) * Self::MARGIN / self.rate
+    }
+}
@@ -0,0 +1,228 @@
+//! Lane counts inferred from the measured width and the rdcl attributes of every edge.
+//!
+//! The rules are tried in order and the first one giving an answer wins:
+//!
+//! 1. `measured`: enough rays of the edge hit the FGD road edges to trust the median width
+//! 2. `rank width`: the width rank (`rnkWidth`) of the road
+//! 3. `neighbours`: the fewest lanes of the adjacent edges of the same road class decided by 1 or 2
+//! 4. `road class`: a typical lane count of the road category (`rdCtg`)
+//! 5. `default`: a single lane
+//!
+//! Unmeasured roads end up with few lanes rather than many, so they do not attract traffic. The
+//! lanes are those of each open direction, so a one-way road gets its whole width.
+
+use crate::{Network, Oneway};
+
+#[derive(Debug, Clone, Copy)]
+pub struct Options {
+    /// fewest rays hitting the FGD lines for a measured width
+    pub min_samples: usize,
+    /// smallest share of rays hitting the FGD lines for a measured width
+    pub min_coverage: f64,
+}
+
+impl Default for Options {
+    fn default() -> Self {
+        Self {
+            min_samples: 3,
+            min_coverage: 0.5,
+        }
+    }
+}
+
+/// What is known about the width of one edge.
+#[derive(Debug, Clone, Default)]
+pub struct Evidence {
+    /// median width in metres
+    pub width: Option<f64>,
+    pub samples: usize,
+    pub rays: usize,
+    /// rdcl `rdCtg`
+    pub rdctg: Option<String>,
+    /// rdcl `rnkWidth`
+    pub rnkwidth: Option<String>,
+    pub oneway: Oneway,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Rule {
+    Measured,
+    RankWidth,
+    Neighbours,
+    RoadClass,
+    Default,
+}
+
+impl Rule {
+    pub const ALL: [Rule; 5] = [
+        Rule::Measured,
+        Rule::RankWidth,
+        Rule::Neighbours,
+        Rule::RoadClass,
+        Rule::Default,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Rule::Measured => "measured",
+            Rule::RankWidth => "rank width",
+            Rule::Neighbours => "neighbours",
+            Rule::RoadClass => "road class",
+            Rule::Default => "default",
+        }
+    }
+}
+
+/// Lanes of each direction of a road of the given width in metres, one lane per 3 m shared by the
+/// open directions.
+pub fn from_width(width: f64, oneway: Oneway) -> u32 {
+    let lanes = (width.clamp(3.0, 18.0) / 3.0).ceil() as u32;
+    match oneway {
+        Oneway::Both => lanes.div_ceil(2),
+        Oneway::Forward | Oneway::Backward => lanes,
+    }
+}
+
+/// Representative width of an `rnkWidth` rank.
+fn rank_width(rnkwidth: &str) -> Option<f64> {
+    match rnkwidth {
+        "3m未満" => Some(2.5),
+        "3m-5.5m未満" => Some(4.25),
+        "5.5m-13m未満" => Some(9.25),
+        "13m-19.5m未満" => Some(16.25),
+        "19.5m以上" => Some(19.5),
+        _ => None,
+    }
+}
+
+/// Typical lanes of an `rdCtg` category.
+fn class_lanes(rdctg: &str) -> Option<u32> {
+    match rdctg {
+        "高速自動車国道等" | "国道" => Some(2),
+        "都道府県道" | "市区町村道等" => Some(1),
+        _ => None,
+    }
+}
+
+/// Lanes of every edge of the network and the rule which decided it.
+pub fn infer(network: &Network, evidence: &[Evidence], options: &Options) -> Vec<(u32, Rule)> {
+    let mut lanes = evidence
+        .iter()
+        .map(|evidence| {
+            let measured = evidence.samples >= options.min_samples
+                && evidence.samples as f64 >= options.min_coverage * evidence.rays as f64;
+
+            match (measured, evidence.width) {
+                (true, Some(width)) => Some((from_width(width, evidence.oneway), Rule::Measured)),
+                _ => {
+                    let width = evidence.rnkwidth.as_deref().and_then(rank_width)?;
+                    Some((from_width(width, evidence.oneway), Rule::RankWidth))
+                }
+            }
+        })
+        .collect::<Vec<_>>();
+
+    let mut incident = vec![vec![]; network.nodes.len()];
+    for (i, edge) in network.edges.iter().enumerate() {
+        incident[edge.n1].push(i);
+        incident[edge.n2].push(i);
+    }
+
+    // neighbours are only taken from edges decided by the edge's own evidence, and open in as many
+    // directions
+    let decided = lanes.clone();
+    for (i, edge) in network.edges.iter().enumerate() {
+        if lanes[i].is_some() {
+            continue;
+        }
+
+        lanes[i] = incident[edge.n1]
+            .iter()
+            .chain(incident[edge.n2].iter())
+            .filter(|j| **j != i && evidence[**j].rdctg == evidence[i].rdctg)
+            .filter(|j| {
+                (evidence[**j].oneway == Oneway::Both) == (evidence[i].oneway == Oneway::Both)
+            })
+            .filter_map(|j| decided[*j])
+            .map(|(lanes, _)| lanes)
+            .min()
+            .map(|lanes| (lanes, Rule::Neighbours));
+    }
+
+    lanes
+        .into_iter()
+        .zip(evidence)
+        .map(|(lanes, evidence)| {
+            lanes
+                .or_else(|| {
+                    let lanes = evidence.rdctg.as_deref().and_then(class_lanes)?;
+                    Some((lanes, Rule::RoadClass))
+                })
+                .unwrap_or((1, Rule::Default))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// `count` edges in a row.
+    fn network(count: usize) -> Network {
+        let nodes = (0..=count).map(|i| (i as f64, 0.0)).collect::<Vec<_>>();
+        let edges = (0..count).map(|i| (i, i + 1)).collect::<Vec<_>>();
+        crate::testing::network(&nodes, &edges)
+    }
+
+    fn measured(width: f64, oneway: Oneway) -> Evidence {
+        Evidence {
+            width: Some(width),
+            samples: 10,
+            rays: 10,
+            oneway,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn splits_the_width_between_two_directions() {
+        let evidence = [measured(6.0, Oneway::Both), measured(12.0, Oneway::Both)];
+        let lanes = infer(&network(2), &evidence, &Options::default());
+        assert_eq!(lanes, vec![(1, Rule::Measured), (2, Rule::Measured)]);
+    }
+
+    #[test]
+    fn gives_the_whole_width_to_a_oneway_direction() {
+        let evidence = [
+            measured(6.0, Oneway::Forward),
+            measured(12.0, Oneway::Backward),
+        ];
+        let lanes = infer(&network(2), &evidence, &Options::default());
+        assert_eq!(lanes, vec![(2, Rule::Measured), (4, Rule::Measured)]);
+    }
+
+    #[test]
+    fn takes_neighbours_open_in_as_many_directions() {
+        let unknown = |oneway| Evidence {
+            oneway,
+            ..Default::default()
+        };
+        let evidence = [
+            measured(12.0, Oneway::Forward),
+            unknown(Oneway::Both),
+            unknown(Oneway::Forward),
+        ];
+        let lanes = infer(&network(3), &evidence, &Options::default());
+        assert_eq!(lanes[1], (1, Rule::Default));
+        assert_eq!(lanes[2], (1, Rule::Default));
+
+        let evidence = [
+            measured(12.0, Oneway::Forward),
+            unknown(Oneway::Forward),
+            unknown(Oneway::Both),
+        ];
+        let lanes = infer(&network(3), &evidence, &Options::default());
+        assert_eq!(lanes[1], (4, Rule::Neighbours));
+        assert_eq!(lanes[2], (1, Rule::Default));
+    }
+}
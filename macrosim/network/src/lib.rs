@@ -0,0 +1,153 @@
+//! Road network model shared by the macrosim stages, independent of postgresql.
+
+pub mod alt;
+pub mod alternative;
+pub mod assign;
+pub mod build;
+pub mod class;
+pub mod contract;
+pub mod cost;
+pub mod lanes;
+mod projection;
+#[cfg(any(test, feature = "testing"))]
+pub mod testing;
+pub mod turn;
+pub mod width;
+
+/// Road network as nodes and edges between them.
+#[derive(Debug, Clone, Default)]
+pub struct Network {
+    /// `(lon, lat)` of each node
+    pub nodes: Vec<(f64, f64)>,
+    pub edges: Vec<Edge>,
+}
+
+/// An edge runs from `n1` to `n2` in the digitising direction of its source line.
+#[derive(Debug, Clone)]
+pub struct Edge {
+    pub n1: usize,
+    pub n2: usize,
+    /// geodesic length in metres
+    pub distance: f64,
+    /// id of the source line, `road.rdcl` when built from the database
+    pub source: i32,
+}
+
+/// Directions in which an edge may be travelled.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Oneway {
+    #[default]
+    Both,
+    /// from `n1` to `n2` only
+    Forward,
+    /// from `n2` to `n1` only
+    Backward,
+}
+
+impl Oneway {
+    pub fn allows(&self, forward: bool) -> bool {
+        match self {
+            Oneway::Both => true,
+            Oneway::Forward => forward,
+            Oneway::Backward => !forward,
+        }
+    }
+}
+
+/// Attributes of one direction of an edge.
+#[derive(Debug, Clone, Copy)]
+pub struct Direction {
+    pub lanes: u32,
+    /// free flow speed in metres per second
+    pub speed: f64,
+    /// vehicles per hour
+    pub capacity: f64,
+}
+
+/// One direction of an edge in the routing graph.
+#[derive(Debug, Clone, Copy, serde::Serialize, serde::Deserialize)]
+pub struct Link {
+    /// index of the edge in the network, its id minus one
+    pub edge: u32,
+    /// travelled from `n1` to `n2`
+    pub forward: bool,
+    /// geodesic length in metres
+    pub distance: f64,
+    pub lanes: u32,
+    /// free flow speed in metres per second
+    pub speed: f64,
+    /// vehicles per hour
+    pub capacity: f64,
+}
+
+pub type Graph = petgraph::Graph<(f64, f64), Link, petgraph::Directed>;
+
+impl Network {
+    /// The routing graph with a link for every direction of an edge which may be travelled.
+    pub fn graph(&self, directions: impl Fn(usize, bool) -> Option<Direction>) -> Graph {
+        let mut graph = petgraph::Graph::with_capacity(self.nodes.len(), self.edges.len() * 2);
+
+        for node in &self.nodes {
+            graph.add_node(*node);
+        }
+
+        for (i, edge) in self.edges.iter().enumerate() {
+            for forward in [true, false] {
+                let direction = match directions(i, forward) {
+                    Some(inner) => inner,
+                    None => continue,
+                };
+
+                let (from, to) = match forward {
+                    true => (edge.n1, edge.n2),
+                    false => (edge.n2, edge.n1),
+                };
+
+                graph.add_edge(
+                    petgraph::graph::NodeIndex::new(from),
+                    petgraph::graph::NodeIndex::new(to),
+                    Link {
+                        edge: i as u32,
+                        forward,
+                        distance: edge.distance,
+                        lanes: direction.lanes,
+                        speed: direction.speed,
+                        capacity: direction.capacity,
+                    },
+                );
+            }
+        }
+
+        graph
+    }
+}
+
+/// SQL of the road shape of a link joined to its edge `e`, reversed when the `forward` column is
+/// false as edges follow their road shape from `n1` to `n2`.
+pub fn link_geom(forward: &str) -> String {
+    format!(
+        "CASE WHEN {} THEN e.geom ELSE ST_Reverse(e.geom) END",
+        forward
+    )
+}
+
+/// Links travelled by a path of graph edge indices, like the paths of `path.bin`.
+pub fn links<'a>(graph: &'a Graph, path: &[u32]) -> Vec<&'a Link> {
+    path.iter()
+        .map(|link| &graph[petgraph::graph::EdgeIndex::new(*link as usize)])
+        .collect()
+}
+
+/// Nodes travelled along `links` starting at `from`.
+pub fn nodes(
+    graph: &Graph,
+    from: petgraph::graph::NodeIndex,
+    links: &[petgraph::graph::EdgeIndex],
+) -> Vec<u32> {
+    let mut nodes = vec![from.index() as u32];
+    for link in links {
+        let (_, head) = graph.edge_endpoints(*link).unwrap();
+        nodes.push(head.index() as u32);
+    }
+    nodes
+}
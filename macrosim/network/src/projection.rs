@@ -0,0 +1,37 @@
+//! Local planar coordinates, so tolerances and lengths can be given in metres.
+
+pub(crate) type Point = [f64; 2];
+
+/// Equirectangular projection around the mean latitude, in metres.
+pub(crate) struct Projection {
+    kx: f64,
+    ky: f64,
+}
+
+impl Projection {
+    pub(crate) fn around(lats: impl Iterator<Item = f64>) -> Self {
+        let (sum, count) = lats.fold((0.0, 0usize), |(sum, count), lat| (sum + lat, count + 1));
+        let lat: f64 = if count > 0 { sum / count as f64 } else { 0.0 };
+
+        Self {
+            kx: 111_320.0 * lat.to_radians().cos(),
+            ky: 110_574.0,
+        }
+    }
+
+    pub(crate) fn forward(&self, c: &geo::Coord<f64>) -> Point {
+        [c.x * self.kx, c.y * self.ky]
+    }
+
+    pub(crate) fn inverse(&self, p: &Point) -> (f64, f64) {
+        (p[0] / self.kx, p[1] / self.ky)
+    }
+}
+
+pub(crate) fn coord(p: &Point) -> geo::Coord<f64> {
+    geo::coord! { x: p[0], y: p[1] }
+}
+
+pub(crate) fn distance_2(a: &Point, b: &Point) -> f64 {
+    (a[0] - b[0]).powi(2) + (a[1] - b[1]).powi(2)
+}
@@ -0,0 +1,31 @@
+//! Small networks for the tests of this crate and the stages built on it.
+
+use crate::{Direction, Edge, Graph, Network, Oneway};
+
+/// Network of the `(lon, lat)` nodes and the `(n1, n2)` edges, each one 100 m long.
+pub fn network(nodes: &[(f64, f64)], edges: &[(usize, usize)]) -> Network {
+    Network {
+        nodes: nodes.to_vec(),
+        edges: edges
+            .iter()
+            .map(|(n1, n2)| Edge {
+                n1: *n1,
+                n2: *n2,
+                distance: 100.0,
+                source: 0,
+            })
+            .collect(),
+    }
+}
+
+/// Routing graph of the network with single lane links at 10 m/s, travelled as `oneway` allows.
+pub fn graph(network: &Network, oneway: Oneway, capacity: f64) -> Graph {
+    network.graph(|_, forward| match oneway.allows(forward) {
+        true => Some(Direction {
+            lanes: 1,
+            speed: 10.0,
+            capacity,
+        }),
+        false => None,
+    })
+}
@@ -0,0 +1,448 @@
+//! Movements between links at intersections.
+//!
+//! Every link of the routing graph is joined to the links leaving its end node, except for banned
+//! turns, and each movement is classified by the change of heading between the two links so left
+//! and right turns and U-turns can carry their own penalty. Headings are taken along the last
+//! segment of the road shape entering the intersection and the first one leaving it, or between
+//! the end nodes of a link without a shape.
+//!
+//! Routing on the movements is a search over links instead of nodes, see [`Turns::astar`]. The
+//! microsimulation reads them from `turn.bin` to move its agents from one link into the next,
+//! waiting out the penalty of every movement.
+
+use crate::{Graph, Link};
+
+use petgraph::visit::EdgeRef;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Movement {
+    Straight,
+    Left,
+    Right,
+    UTurn,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct Options {
+    /// largest heading change in degrees still going straight
+    pub straight: f64,
+    /// smallest heading change in degrees making a U-turn
+    pub u_turn: f64,
+    /// U-turns away from dead ends are allowed
+    pub u_turns: bool,
+    /// penalties added to the routing cost
+    pub left_penalty: f64,
+    pub right_penalty: f64,
+    pub u_turn_penalty: f64,
+}
+
+impl Default for Options {
+    fn default() -> Self {
+        Self {
+            straight: 30.0,
+            u_turn: 150.0,
+            u_turns: true,
+            left_penalty: 5.0,
+            right_penalty: 10.0,
+            u_turn_penalty: 30.0,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Stats {
+    pub movements: usize,
+    pub banned: usize,
+    pub u_turns: usize,
+}
+
+/// Movements out of every link, as the next link index and the penalty.
+#[derive(Debug, Clone, Default, serde::Serialize, serde::Deserialize)]
+pub struct Turns {
+    pub next: Vec<Vec<(u32, f64)>>,
+}
+
+/// Heading in radians from `a` to `b`, east is zero and north a quarter turn.
+fn heading(a: (f64, f64), b: (f64, f64)) -> f64 {
+    let dx = (b.0 - a.0) * a.1.to_radians().cos();
+    (b.1 - a.1).atan2(dx)
+}
+
+/// Headings of link `e` leaving its start node and entering its end node, along the shape of its
+/// edge in `shapes` or its chord when the edge has none.
+fn headings(
+    graph: &Graph,
+    e: petgraph::graph::EdgeIndex,
+    shapes: &[Vec<(f64, f64)>],
+) -> (f64, f64) {
+    let (n0, n1) = graph.edge_endpoints(e).unwrap();
+    let chord = heading(graph[n0], graph[n1]);
+
+    let link = graph[e];
+    let shape = match shapes.get(link.edge as usize) {
+        Some(inner) => inner,
+        None => return (chord, chord),
+    };
+
+    // the nearest points which differ from each end, skipping repeated coordinates
+    let first = shape
+        .first()
+        .and_then(|p| shape.iter().find(|q| *q != p).map(|q| (*p, *q)));
+    let last = shape
+        .last()
+        .and_then(|p| shape.iter().rev().find(|q| *q != p).map(|q| (*q, *p)));
+
+    match (first, last, link.forward) {
+        (Some((a, b)), Some((c, d)), true) => (heading(a, b), heading(c, d)),
+        (Some((a, b)), Some((c, d)), false) => (heading(d, c), heading(b, a)),
+        _ => (chord, chord),
+    }
+}
+
+/// Movement from link `a` into link `b`, with the road shapes of the edges from `n1` to `n2` in
+/// `shapes`, which may be empty.
+pub fn classify(
+    graph: &Graph,
+    shapes: &[Vec<(f64, f64)>],
+    a: petgraph::graph::EdgeIndex,
+    b: petgraph::graph::EdgeIndex,
+    options: &Options,
+) -> Movement {
+    let (la, lb) = (graph[a], graph[b]);
+    if la.edge == lb.edge && la.forward != lb.forward {
+        return Movement::UTurn;
+    }
+
+    let (_, h0) = headings(graph, a, shapes);
+    let (h1, _) = headings(graph, b, shapes);
+
+    // counter-clockwise change of heading in degrees, between -180 and 180
+    let d = (h1 - h0).to_degrees().rem_euclid(360.0);
+    let d = if d > 180.0 { d - 360.0 } else { d };
+
+    if d.abs() <= options.straight {
+        Movement::Straight
+    } else if d.abs() >= options.u_turn {
+        Movement::UTurn
+    } else if d > 0.0 {
+        Movement::Left
+    } else {
+        Movement::Right
+    }
+}
+
+/// Movements of the graph, without the ones `banned` returns true for. See [`classify`] for
+/// `shapes`.
+pub fn expand(
+    graph: &Graph,
+    shapes: &[Vec<(f64, f64)>],
+    banned: impl Fn(&Link, &Link) -> bool,
+    options: &Options,
+) -> (Turns, Stats) {
+    let mut turns = Turns {
+        next: vec![vec![]; graph.edge_count()],
+    };
+    let mut stats = Stats::default();
+
+    for a in graph.edge_references() {
+        let via = a.target();
+        let dead_end = graph.neighbors_undirected(via).all(|n| n == a.source());
+
+        for b in graph.edges(via) {
+            if banned(a.weight(), b.weight()) {
+                stats.banned += 1;
+                continue;
+            }
+
+            let penalty = match classify(graph, shapes, a.id(), b.id(), options) {
+                Movement::Straight => 0.0,
+                Movement::Left => options.left_penalty,
+                Movement::Right => options.right_penalty,
+                Movement::UTurn if options.u_turns || dead_end => {
+                    stats.u_turns += 1;
+                    options.u_turn_penalty
+                }
+                Movement::UTurn => {
+                    stats.banned += 1;
+                    continue;
+                }
+            };
+
+            turns.next[a.id().index()].push((b.id().index() as u32, penalty));
+            stats.movements += 1;
+        }
+    }
+
+    (turns, stats)
+}
+
+/// Link waiting in the search heap, the one with the lowest score first.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) struct State {
+    pub(crate) score: f64,
+    pub(crate) cost: f64,
+    pub(crate) link: u32,
+}
+
+impl Eq for State {}
+
+impl Ord for State {
+    fn cmp(&self, other: &Self) -> std::cmp::Ordering {
+        other.score.total_cmp(&self.score)
+    }
+}
+
+impl PartialOrd for State {
+    fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Turns {
+    /// Penalty of the movement from link `a` into link `b`, `None` when it is not allowed.
+    pub fn penalty(
+        &self,
+        a: petgraph::graph::EdgeIndex,
+        b: petgraph::graph::EdgeIndex,
+    ) -> Option<f64> {
+        self.next[a.index()]
+            .iter()
+            .find(|(next, _)| *next as usize == b.index())
+            .map(|(_, penalty)| *penalty)
+    }
+
+    /// Cheapest path from `from` to `to` entering every node through an allowed movement, as the
+    /// cost and the links travelled, like `petgraph::algo::astar`. See [`crate::nodes`] for the
+    /// nodes travelled.
+    pub fn astar(
+        &self,
+        graph: &Graph,
+        from: petgraph::graph::NodeIndex,
+        to: petgraph::graph::NodeIndex,
+        cost: impl Fn(petgraph::graph::EdgeIndex, &Link) -> f64,
+        heuristic: impl Fn(petgraph::graph::NodeIndex) -> f64,
+    ) -> Option<(f64, Vec<petgraph::graph::EdgeIndex>)> {
+        self.search(graph, from, to, cost, heuristic).0
+    }
+
+    /// [`Turns::astar`] and the number of links settled on the way.
+    pub fn search(
+        &self,
+        graph: &Graph,
+        from: petgraph::graph::NodeIndex,
+        to: petgraph::graph::NodeIndex,
+        cost: impl Fn(petgraph::graph::EdgeIndex, &Link) -> f64,
+        heuristic: impl Fn(petgraph::graph::NodeIndex) -> f64,
+    ) -> (Option<(f64, Vec<petgraph::graph::EdgeIndex>)>, usize) {
+        if from == to {
+            return (Some((0.0, vec![])), 0);
+        }
+
+        let mut settled = 0;
+
+        let mut costs = vec![f64::INFINITY; graph.edge_count()];
+        let mut previous = vec![u32::MAX; graph.edge_count()];
+        let mut heap = std::collections::BinaryHeap::new();
+
+        for edge in graph.edges(from) {
+            let link = edge.id().index();
+            let c = cost(edge.id(), edge.weight());
+            if c < costs[link] {
+                costs[link] = c;
+                heap.push(State {
+                    score: c + heuristic(edge.target()),
+                    cost: c,
+                    link: link as u32,
+                });
+            }
+        }
+
+        while let Some(State {
+            cost: current,
+            link,
+            ..
+        }) = heap.pop()
+        {
+            if current > costs[link as usize] {
+                continue;
+            }
+            let index = petgraph::graph::EdgeIndex::new(link as usize);
+            let (_, head) = graph.edge_endpoints(index).unwrap();
+            settled += 1;
+
+            if head == to {
+                return (Some((current, trace(&previous, link))), settled);
+            }
+
+            for &(next, penalty) in &self.next[link as usize] {
+                let edge = petgraph::graph::EdgeIndex::new(next as usize);
+                let c = current + penalty + cost(edge, &graph[edge]);
+                if c < costs[next as usize] {
+                    costs[next as usize] = c;
+                    previous[next as usize] = link;
+                    let (_, target) = graph.edge_endpoints(edge).unwrap();
+                    heap.push(State {
+                        score: c + heuristic(target),
+                        cost: c,
+                        link: next,
+                    });
+                }
+            }
+        }
+
+        (None, settled)
+    }
+
+    /// Cheapest paths from `from` to every node of `to` out of a single search, which stops once
+    /// all of them are reached.
+    pub fn tree(
+        &self,
+        graph: &Graph,
+        from: petgraph::graph::NodeIndex,
+        to: &[petgraph::graph::NodeIndex],
+        cost: impl Fn(petgraph::graph::EdgeIndex, &Link) -> f64,
+    ) -> Vec<Option<(f64, Vec<petgraph::graph::EdgeIndex>)>> {
+        // link by which every node was first reached
+        let mut reached = std::collections::HashMap::new();
+        let mut remaining = to
+            .iter()
+            .filter(|n| **n != from)
+            .collect::<std::collections::HashSet<_>>();
+
+        let mut costs = vec![f64::INFINITY; graph.edge_count()];
+        let mut previous = vec![u32::MAX; graph.edge_count()];
+        let mut heap = std::collections::BinaryHeap::new();
+
+        for edge in graph.edges(from) {
+            let link = edge.id().index();
+            let c = cost(edge.id(), edge.weight());
+            if c < costs[link] {
+                costs[link] = c;
+                heap.push(State {
+                    score: c,
+                    cost: c,
+                    link: link as u32,
+                });
+            }
+        }
+
+        while let Some(State {
+            cost: current,
+            link,
+            ..
+        }) = heap.pop()
+        {
+            if remaining.is_empty() {
+                break;
+            }
+            if current > costs[link as usize] {
+                continue;
+            }
+
+            let index = petgraph::graph::EdgeIndex::new(link as usize);
+            let (_, head) = graph.edge_endpoints(index).unwrap();
+            if remaining.remove(&head) {
+                reached.insert(head, link);
+            }
+
+            for &(next, penalty) in &self.next[link as usize] {
+                let edge = petgraph::graph::EdgeIndex::new(next as usize);
+                let c = current + penalty + cost(edge, &graph[edge]);
+                if c < costs[next as usize] {
+                    costs[next as usize] = c;
+                    previous[next as usize] = link;
+                    heap.push(State {
+                        score: c,
+                        cost: c,
+                        link: next,
+                    });
+                }
+            }
+        }
+
+        to.iter()
+            .map(|n| match reached.get(n) {
+                _ if *n == from => Some((0.0, vec![])),
+                Some(link) => Some((costs[*link as usize], trace(&previous, *link))),
+                None => None,
+            })
+            .collect()
+    }
+}
+
+/// Links travelled up to `link`.
+fn trace(previous: &[u32], link: u32) -> Vec<petgraph::graph::EdgeIndex> {
+    let mut links = vec![];
+    let mut link = link;
+    while link != u32::MAX {
+        links.push(petgraph::graph::EdgeIndex::new(link as usize));
+        link = previous[link as usize];
+    }
+
+    links.reverse();
+    links
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use petgraph::graph::EdgeIndex;
+
+    /// Two edges in a row running east, the first bending south into the shared node.
+    fn bend() -> (Graph, Vec<Vec<(f64, f64)>>) {
+        let network =
+            crate::testing::network(&[(0.0, 0.0), (0.002, 0.0), (0.004, 0.0)], &[(0, 1), (1, 2)]);
+        let graph = crate::testing::graph(&network, crate::Oneway::Both, 1000.0);
+        let shapes = vec![
+            vec![(0.0, 0.0), (0.002, 0.001), (0.002, 0.0)],
+            vec![(0.002, 0.0), (0.004, 0.0)],
+        ];
+        (graph, shapes)
+    }
+
+    #[test]
+    fn turns_along_the_chord_without_shapes() {
+        let (graph, _) = bend();
+        let options = Options::default();
+        let movement = classify(&graph, &[], EdgeIndex::new(0), EdgeIndex::new(2), &options);
+        assert_eq!(movement, Movement::Straight);
+    }
+
+    #[test]
+    fn turns_along_the_shape_at_the_intersection() {
+        let (graph, shapes) = bend();
+        let options = Options::default();
+
+        // heading south into the node and leaving east
+        let movement = classify(
+            &graph,
+            &shapes,
+            EdgeIndex::new(0),
+            EdgeIndex::new(2),
+            &options,
+        );
+        assert_eq!(movement, Movement::Left);
+
+        // heading west into the node and leaving north, against the shape
+        let movement = classify(
+            &graph,
+            &shapes,
+            EdgeIndex::new(3),
+            EdgeIndex::new(1),
+            &options,
+        );
+        assert_eq!(movement, Movement::Right);
+    }
+
+    #[test]
+    fn penalises_the_shape_movement() {
+        let (graph, shapes) = bend();
+        let options = Options::default();
+        let (turns, _) = expand(&graph, &shapes, |_, _| false, &options);
+        assert_eq!(
+            turns.penalty(EdgeIndex::new(0), EdgeIndex::new(2)),
+            Some(options.left_penalty)
+        );
+    }
+}
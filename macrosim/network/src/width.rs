@@ -0,0 +1,173 @@
+//! Road width measured against the FGD road edge lines.
+//!
+//! Sample points are spread along every edge at most `spacing` apart, and from each one a ray is
+//! cast perpendicular to the edge on both sides, like `width.sql` did. The width at a sample point
+//! is the sum of the distances to the nearest FGD line hit on each side, and the samples of an edge
+//! are summarised into their median, minimum and maximum.
+
+use crate::projection::{coord, Point, Projection};
+
+#[derive(Debug, Clone, Copy)]
+pub struct Options {
+    /// length of each ray in metres
+    pub ray: f64,
+    /// largest distance between sample points in metres
+    pub spacing: f64,
+    pub threads: usize,
+}
+
+impl Default for Options {
+    fn default() -> Self {
+        Self {
+            ray: 15.0,
+            spacing: 4.0,
+            threads: std::thread::available_parallelism().map_or(1, |n| n.get()),
+        }
+    }
+}
+
+/// Width statistics of one edge in metres.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Width {
+    /// sample points along the edge
+    pub rays: usize,
+    /// sample points where a ray hit an FGD line on at least one side
+    pub samples: usize,
+    pub median: Option<f64>,
+    pub min: Option<f64>,
+    pub max: Option<f64>,
+}
+
+type Segment = rstar::primitives::Line<Point>;
+
+/// Width of every edge given as a `(lon, lat)` polyline, measured against the FGD lines.
+///
+/// Panics unless `ray` and `spacing` are greater than zero.
+pub fn estimate(
+    edges: &[Vec<(f64, f64)>],
+    fgd: &[geo::LineString<f64>],
+    options: &Options,
+) -> Vec<Width> {
+    assert!(options.ray > 0.0, "ray must be greater than zero");
+    assert!(options.spacing > 0.0, "spacing must be greater than zero");
+
+    let projection = Projection::around(edges.iter().flatten().map(|c| c.1));
+
+    let segments = fgd
+        .iter()
+        .flat_map(|line| line.lines())
+        .map(|line| {
+            Segment::new(
+                projection.forward(&line.start),
+                projection.forward(&line.end),
+            )
+        })
+        .collect::<Vec<_>>();
+    let tree = rstar::RTree::bulk_load(segments);
+
+    let split_len = edges.len().div_ceil(options.threads.max(1)).max(1);
+
+    std::thread::scope(|scope| {
+        let threads = edges
+            .chunks(split_len)
+            .map(|edges| {
+                let (tree, projection) = (&tree, &projection);
+                scope.spawn(move || {
+                    edges
+                        .iter()
+                        .map(|edge| {
+                            let points = edge
+                                .iter()
+                                .map(|(x, y)| projection.forward(&geo::coord! { x: *x, y: *y }))
+                                .collect::<Vec<_>>();
+                            measure(tree, &points, options)
+                        })
+                        .collect::<Vec<_>>()
+                })
+            })
+            .collect::<Vec<_>>();
+
+        threads
+            .into_iter()
+            .flat_map(|thread| thread.join().unwrap())
+            .collect()
+    })
+}
+
+fn measure(tree: &rstar::RTree<Segment>, points: &[Point], options: &Options) -> Width {
+    let mut width = Width::default();
+    let mut samples = vec![];
+
+    for pair in points.windows(2) {
+        let (a, b) = (pair[0], pair[1]);
+        let length = (b[0] - a[0]).hypot(b[1] - a[1]);
+        if length == 0.0 {
+            continue;
+        }
+
+        let d = [(b[0] - a[0]) / length, (b[1] - a[1]) / length];
+        let normal = [-d[1], d[0]];
+        let count = (length / options.spacing).ceil().max(1.0) as usize;
+
+        for k in 0..count {
+            let t = length * (k as f64 + 0.5) / count as f64;
+            let origin = [a[0] + d[0] * t, a[1] + d[1] * t];
+            width.rays += 1;
+
+            let sides = [1.0, -1.0].map(|sign| {
+                let end = [
+                    origin[0] + sign * normal[0] * options.ray,
+                    origin[1] + sign * normal[1] * options.ray,
+                ];
+                hit(tree, origin, end)
+            });
+
+            if sides.iter().any(Option::is_some) {
+                samples.push(sides.iter().flatten().sum::<f64>());
+            }
+        }
+    }
+
+    if samples.is_empty() {
+        return width;
+    }
+
+    samples.sort_by(f64::total_cmp);
+    let n = samples.len();
+
+    width.samples = n;
+    width.median = Some(if n % 2 == 1 {
+        samples[n / 2]
+    } else {
+        (samples[n / 2 - 1] + samples[n / 2]) / 2.0
+    });
+    width.min = samples.first().copied();
+    width.max = samples.last().copied();
+
+    width
+}
+
+/// Distance from the origin to the nearest FGD line crossed by the ray.
+fn hit(tree: &rstar::RTree<Segment>, origin: Point, end: Point) -> Option<f64> {
+    let ray = geo::Line::new(coord(&origin), coord(&end));
+    let envelope = rstar::AABB::from_corners(origin, end);
+
+    tree.locate_in_envelope_intersecting(&envelope)
+        .filter_map(|segment| {
+            let other = geo::Line::new(coord(&segment.from), coord(&segment.to));
+
+            match geo::line_intersection::line_intersection(ray, other)? {
+                geo::line_intersection::LineIntersection::SinglePoint { intersection, .. } => {
+                    Some((intersection.x - origin[0]).hypot(intersection.y - origin[1]))
+                }
+                geo::line_intersection::LineIntersection::Collinear { intersection } => {
+                    let start =
+                        (intersection.start.x - origin[0]).hypot(intersection.start.y - origin[1]);
+                    let end =
+                        (intersection.end.x - origin[0]).hypot(intersection.end.y - origin[1]);
+                    Some(start.min(end))
+                }
+            }
+        })
+        .min_by(f64::total_cmp)
+}
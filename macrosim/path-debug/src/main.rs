@@ -2,13 +2,10 @@ const SAMPLE_COUNT: usize = 20_000;
 
 #[tokio::main]
 async fn main() {
-    type Data = (
-        petgraph::Graph<(f64, f64), (f64, u32), petgraph::Undirected>,
-        Vec<Vec<u32>>,
-    );
+    type Data = (network::Graph, Vec<Vec<u32>>, Vec<i32>);
 
     let bytes = std::fs::read("path.bin").unwrap();
-    let (graph, paths): Data = postcard::from_bytes(&bytes).unwrap();
+    let (graph, paths, _): Data = postcard::from_bytes(&bytes).unwrap();
     drop(bytes);
 
     #[rustfmt::skip]
@@ -32,24 +29,23 @@ async fn main() {
     let indicator = indicatif::ProgressBar::new(SAMPLE_COUNT as u64);
 
     let mut rng = rand::thread_rng();
+    // unroutable plans have an empty path
+    let paths = paths
+        .into_iter()
+        .filter(|path| !path.is_empty())
+        .collect::<Vec<_>>();
     let paths = rand::seq::SliceRandom::choose_multiple(paths.as_slice(), &mut rng, SAMPLE_COUNT);
-    for path in paths {
-        let mut xs = vec![];
-        let mut ys = vec![];
-
-        for n in path {
-            let (x, y) = *graph
-                .node_weight(petgraph::graph::NodeIndex::new(*n as usize))
-                .unwrap();
+    let sql = format!("INSERT INTO path (geom) SELECT ST_MakeLine({} ORDER BY p.seq) FROM unnest($1::Int4[], $2::Bool[]) WITH ORDINALITY AS p(id, forward, seq) JOIN edge e ON e.id = p.id", network::link_geom("p.forward"));
 
-            xs.push(x);
-            ys.push(y);
-        }
+    for path in paths {
+        let links = network::links(&graph, path);
+        let ids = links.iter().map(|l| l.edge as i32 + 1).collect::<Vec<_>>();
+        let forwards = links.iter().map(|l| l.forward).collect::<Vec<_>>();
 
         #[rustfmt::skip]
-        sqlx::query("INSERT INTO path (geom) SELECT ST_MakeLine(geoms) FROM (SELECT array_agg(ST_Point(x, y)) AS geoms FROM unnest($1, $2) AS _(x, y))")
-            .bind(xs)
-            .bind(ys)
+        sqlx::query(&sql)
+            .bind(ids)
+            .bind(forwards)
             .execute(&pool)
             .await
             .unwrap();
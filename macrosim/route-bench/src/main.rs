@@ -0,0 +1,124 @@
+use clap::Parser;
+use rand::SeedableRng;
+
+/// Compare the A* heuristics on the graph and OD pairs of `path.bin`.
+#[derive(Debug, clap::Parser)]
+struct Cli {
+    /// routed pairs to sample
+    #[arg(long, default_value_t = 1000)]
+    queries: usize,
+    #[arg(long, default_value_t = 42)]
+    seed: u64,
+    /// routing cost, `time` in seconds or `lanes` for length divided by lanes
+    #[arg(long, default_value_t = network::cost::Strategy::default())]
+    cost: network::cost::Strategy,
+    /// landmarks for the `alt` heuristic, saved in `landmarks.bin` and reused while they match
+    #[arg(long, default_value_t = 8)]
+    landmarks: usize,
+}
+
+fn main() {
+    let cli = Cli::parse();
+
+    type Data = (network::Graph, Vec<Vec<u32>>, Vec<i32>);
+
+    let bytes = std::fs::read("path.bin").unwrap();
+    let (graph, paths, _): Data = postcard::from_bytes(&bytes).unwrap();
+    drop(bytes);
+
+    let mut rng = rand::rngs::StdRng::seed_from_u64(cli.seed);
+    let routed = paths.iter().filter(|path| !path.is_empty());
+    let plans = rand::seq::IteratorRandom::choose_multiple(routed, &mut rng, cli.queries)
+        .into_iter()
+        .map(|path| {
+            let first = petgraph::graph::EdgeIndex::new(path[0] as usize);
+            let last = petgraph::graph::EdgeIndex::new(path[path.len() - 1] as usize);
+            let (n1, _) = graph.edge_endpoints(first).unwrap();
+            let (_, n2) = graph.edge_endpoints(last).unwrap();
+            (n1, n2)
+        })
+        .collect::<Vec<_>>();
+
+    let (turns, _) = network::turn::expand(&graph, &[], |_, _| false, &Default::default());
+    let model = network::cost::Model {
+        strategy: cli.cost,
+        ..Default::default()
+    };
+    let heuristic = network::cost::Heuristic::new(&graph, &model);
+
+    let start = std::time::Instant::now();
+    let (landmarks, reused) = network::alt::Landmarks::load_or_build(
+        std::path::Path::new("landmarks.bin"),
+        &graph,
+        cli.landmarks,
+        |link| model.cost(link),
+    );
+    println!(
+        "[bench stats] landmarks: {}, reused: {}, seconds: {:.3}",
+        landmarks.nodes.len(),
+        reused,
+        start.elapsed().as_secs_f64()
+    );
+
+    println!(
+        "[bench stats] nodes: {}, links: {}, queries: {}, cost: {}",
+        graph.node_count(),
+        graph.edge_count(),
+        plans.len(),
+        cli.cost
+    );
+
+    let mut results = vec![];
+    for name in ["zero", "haversine", "alt"] {
+        let start = std::time::Instant::now();
+        let mut settled = 0;
+        let mut costs = vec![];
+
+        for &(n1, n2) in &plans {
+            let (path, count) = turns.search(
+                &graph,
+                n1,
+                n2,
+                |_, link| model.cost(link),
+                |n| match name {
+                    "zero" => 0.0,
+                    "haversine" => heuristic.estimate(&graph, n, n2),
+                    _ => landmarks
+                        .estimate(n, n2)
+                        .max(heuristic.estimate(&graph, n, n2)),
+                },
+            );
+            settled += count;
+            costs.push(path.map(|(cost, _)| cost));
+        }
+
+        let elapsed = start.elapsed().as_secs_f64();
+        println!(
+            "[bench stats] {}: settled links per query: {:.1}, seconds: {:.3}",
+            name,
+            settled as f64 / plans.len().max(1) as f64,
+            elapsed
+        );
+        results.push((elapsed, costs));
+    }
+
+    // an admissible heuristic finds paths as cheap as the zero one
+    for (name, (elapsed, costs)) in ["haversine", "alt"].iter().zip(results.iter().skip(1)) {
+        let mismatches = results[0]
+            .1
+            .iter()
+            .zip(costs.iter())
+            .filter(|(a, b)| match (a, b) {
+                (Some(a), Some(b)) => (a - b).abs() > 1e-6 * a.max(1.0),
+                (a, b) => a.is_some() != b.is_some(),
+            })
+            .count();
+
+        println!(
+            "[bench stats] {}: speedup: {:.2}, cost mismatches: {}",
+            name,
+            results[0].0 / elapsed,
+            mismatches
+        );
+    }
+}
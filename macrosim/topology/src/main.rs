@@ -0,0 +1,257 @@
+use clap::Parser;
+
+/// Build the `node`, `edge` and `segment` tables from the stitched `road` table.
+#[derive(Debug, clap::Parser)]
+struct Cli {
+    /// distance in metres within which a line end is connected to another line or node
+    #[arg(long, default_value_t = network::build::Options::default().snap)]
+    snap: f64,
+    /// do not split lines crossing on the same level
+    #[arg(long)]
+    no_split: bool,
+    /// also contract degree-2 nodes joining different roads
+    #[arg(long)]
+    across_sources: bool,
+    /// read lines from a GeoJSON file instead of the `road` table, without postgresql
+    #[arg(long)]
+    geojson: Option<std::path::PathBuf>,
+    /// with `--geojson`, GeoJSON file the nodes and edges are written to instead of the tables
+    #[arg(long, default_value = "topology.geojson")]
+    output: std::path::PathBuf,
+}
+
+#[tokio::main]
+async fn main() {
+    let cli = Cli::parse();
+
+    // a GeoJSON input runs offline
+    let pool = match &cli.geojson {
+        Some(_) => None,
+        None => {
+            #[rustfmt::skip]
+            let pool = sqlx::postgres::PgPoolOptions::new()
+                .connect("postgres://postgres:0@localhost/postgres")
+                .await
+                .expect("failed to connect postgresql");
+            Some(pool)
+        }
+    };
+
+    let lines = match &cli.geojson {
+        Some(path) => {
+            let text = std::fs::read_to_string(path).expect("failed to read geojson");
+            network::build::Line::from_geojson(&text).expect("failed to parse geojson")
+        }
+        None => {
+            #[rustfmt::skip]
+            let rows: Vec<(i32, Option<i32>, String)> = sqlx::query_as("SELECT r.rdcl, c.lvorder, ST_AsGeoJSON(r.geom) FROM road r LEFT JOIN rdcl c ON r.rdcl = c.id ORDER BY r.id")
+                .fetch_all(pool.as_ref().unwrap())
+                .await
+                .unwrap();
+
+            rows.into_iter()
+                .map(|(rdcl, lvorder, geom)| {
+                    let geometry = geom.parse::<geojson::Geometry>().unwrap();
+                    network::build::Line {
+                        source: rdcl,
+                        level: lvorder.unwrap_or(0),
+                        geometry: geometry.value.try_into().unwrap(),
+                    }
+                })
+                .collect()
+        }
+    };
+
+    println!("[road stats] lines: {}", lines.len());
+
+    let options = network::build::Options {
+        snap: cli.snap,
+        split: !cli.no_split,
+    };
+    let (network, stats) = network::build::build(&lines, &options);
+
+    println!(
+        "[topology stats] nodes: {}, edges: {}, crossings: {}, snapped: {}",
+        network.nodes.len(),
+        network.edges.len(),
+        stats.crossings,
+        stats.snapped
+    );
+
+    let options = network::contract::Options {
+        across_sources: cli.across_sources,
+    };
+    let contracted = network::contract::contract(&network, &options);
+
+    println!(
+        "[contract stats] nodes: {}, edges: {}",
+        contracted.network.nodes.len(),
+        contracted.network.edges.len()
+    );
+
+    let pool = match pool {
+        Some(inner) => inner,
+        None => {
+            write(&cli.output, &contracted);
+            return;
+        }
+    };
+
+    #[rustfmt::skip]
+    sqlx::query("DROP TABLE IF EXISTS node CASCADE")
+        .execute(&pool)
+        .await
+        .unwrap();
+
+    #[rustfmt::skip]
+    sqlx::query("CREATE TABLE IF NOT EXISTS node (id Serial PRIMARY KEY, geom Geometry(Point, 6668))")
+        .execute(&pool)
+        .await
+        .unwrap();
+
+    // node id is the graph index plus one
+    let nodes = &contracted.network.nodes;
+    let ids = (1..=nodes.len() as i32).collect::<Vec<_>>();
+    let xs = nodes.iter().map(|n| n.0).collect::<Vec<_>>();
+    let ys = nodes.iter().map(|n| n.1).collect::<Vec<_>>();
+
+    #[rustfmt::skip]
+    sqlx::query("INSERT INTO node (id, geom) SELECT id, ST_SetSRID(ST_Point(x, y), 6668) FROM unnest($1, $2, $3) AS _(id, x, y)")
+        .bind(&ids)
+        .bind(&xs)
+        .bind(&ys)
+        .execute(&pool)
+        .await
+        .unwrap();
+
+    #[rustfmt::skip]
+    sqlx::query("CREATE INDEX IF NOT EXISTS node_idx ON node USING GIST(geom)")
+        .execute(&pool)
+        .await
+        .unwrap();
+
+    #[rustfmt::skip]
+    sqlx::query("DROP TABLE IF EXISTS edge CASCADE")
+        .execute(&pool)
+        .await
+        .unwrap();
+
+    #[rustfmt::skip]
+    sqlx::query("CREATE TABLE IF NOT EXISTS edge (id Serial PRIMARY KEY, n1 Int4 REFERENCES node (id), n2 Int4 REFERENCES node (id), distance Float8, rdcl Int4, geom Geometry(LineString, 6668))")
+        .execute(&pool)
+        .await
+        .unwrap();
+
+    // edge id is the graph index plus one
+    let edges = &contracted.network.edges;
+    let ids = (1..=edges.len() as i32).collect::<Vec<_>>();
+    let n1s = edges.iter().map(|e| e.n1 as i32 + 1).collect::<Vec<_>>();
+    let n2s = edges.iter().map(|e| e.n2 as i32 + 1).collect::<Vec<_>>();
+    let distances = edges.iter().map(|e| e.distance).collect::<Vec<_>>();
+    let rdcls = edges.iter().map(|e| e.source).collect::<Vec<_>>();
+    let geoms = contracted
+        .geometry
+        .iter()
+        .map(|coords| line(coords.iter().copied()))
+        .collect::<Vec<_>>();
+
+    #[rustfmt::skip]
+    sqlx::query("INSERT INTO edge (id, n1, n2, distance, rdcl, geom) SELECT id, n1, n2, distance, rdcl, ST_SetSRID(ST_GeomFromGeoJSON(geom), 6668) FROM unnest($1, $2, $3, $4, $5, $6) AS _(id, n1, n2, distance, rdcl, geom)")
+        .bind(&ids)
+        .bind(&n1s)
+        .bind(&n2s)
+        .bind(&distances)
+        .bind(&rdcls)
+        .bind(&geoms)
+        .execute(&pool)
+        .await
+        .unwrap();
+
+    #[rustfmt::skip]
+    sqlx::query("DROP TABLE IF EXISTS segment")
+        .execute(&pool)
+        .await
+        .unwrap();
+
+    // segment id is the index in the uncontracted network plus one, seq the position in its edge
+    #[rustfmt::skip]
+    sqlx::query("CREATE TABLE IF NOT EXISTS segment (id Int4 PRIMARY KEY, edge Int4 REFERENCES edge (id), seq Int4, distance Float8, geom Geometry(LineString, 6668))")
+        .execute(&pool)
+        .await
+        .unwrap();
+
+    let mut ids = vec![];
+    let mut parents = vec![];
+    let mut seqs = vec![];
+    let mut distances = vec![];
+    let mut geoms = vec![];
+    for (i, segments) in contracted.segments.iter().enumerate() {
+        for (seq, segment) in segments.iter().enumerate() {
+            let edge = &network.edges[*segment];
+            ids.push(*segment as i32 + 1);
+            parents.push(i as i32 + 1);
+            seqs.push(seq as i32);
+            distances.push(edge.distance);
+            geoms.push(line([network.nodes[edge.n1], network.nodes[edge.n2]]));
+        }
+    }
+
+    #[rustfmt::skip]
+    sqlx::query("INSERT INTO segment (id, edge, seq, distance, geom) SELECT id, edge, seq, distance, ST_SetSRID(ST_GeomFromGeoJSON(geom), 6668) FROM unnest($1, $2, $3, $4, $5) AS _(id, edge, seq, distance, geom)")
+        .bind(&ids)
+        .bind(&parents)
+        .bind(&seqs)
+        .bind(&distances)
+        .bind(&geoms)
+        .execute(&pool)
+        .await
+        .unwrap();
+}
+
+/// Nodes and edges as GeoJSON features, with the ids the tables would give them.
+fn write(path: &std::path::Path, contracted: &network::contract::Contracted) {
+    let mut features = vec![];
+
+    for (i, node) in contracted.network.nodes.iter().enumerate() {
+        let mut properties = geojson::JsonObject::new();
+        properties.insert("node".to_string(), (i + 1).into());
+        features.push(geojson::Feature {
+            geometry: Some(geojson::Geometry::new(geojson::Value::Point(vec![
+                node.0, node.1,
+            ]))),
+            properties: Some(properties),
+            ..Default::default()
+        });
+    }
+
+    for (i, edge) in contracted.network.edges.iter().enumerate() {
+        let coords = contracted.geometry[i]
+            .iter()
+            .map(|(x, y)| vec![*x, *y])
+            .collect();
+
+        let mut properties = geojson::JsonObject::new();
+        properties.insert("edge".to_string(), (i + 1).into());
+        properties.insert("n1".to_string(), (edge.n1 + 1).into());
+        properties.insert("n2".to_string(), (edge.n2 + 1).into());
+        properties.insert("distance".to_string(), edge.distance.into());
+        properties.insert("rdcl".to_string(), edge.source.into());
+        features.push(geojson::Feature {
+            geometry: Some(geojson::Geometry::new(geojson::Value::LineString(coords))),
+            properties: Some(properties),
+            ..Default::default()
+        });
+    }
+
+    let collection = geojson::FeatureCollection {
+        bbox: None,
+        features,
+        foreign_members: None,
+    };
+    std::fs::write(path, collection.to_string()).expect("failed to write geojson");
+}
+
+fn line(coords: impl IntoIterator<Item = (f64, f64)>) -> String {
+    let coords = coords.into_iter().map(|(x, y)| vec![x, y]).collect();
+    geojson::Geometry::new(geojson::Value::LineString(coords)).to_string()
+}
@@ -0,0 +1,144 @@
+use clap::Parser;
+
+/// Aggregate `assignment.bin` into the `volume` table, one row per travelled direction of an edge.
+#[derive(Debug, clap::Parser)]
+struct Cli {
+    /// also write the loaded links as GeoJSON lines drawn in their direction of travel
+    #[arg(long)]
+    geojson: Option<std::path::PathBuf>,
+    /// also write every link as CSV
+    #[arg(long)]
+    csv: Option<std::path::PathBuf>,
+}
+
+#[tokio::main]
+async fn main() {
+    let cli = Cli::parse();
+
+    type Data = (network::Graph, Vec<Vec<u32>>, Vec<i32>);
+
+    let bytes = std::fs::read("path.bin").unwrap();
+    let (graph, _, _): Data = postcard::from_bytes(&bytes).unwrap();
+    let bytes = std::fs::read("assignment.bin").unwrap();
+    let assignment: network::assign::Assignment = postcard::from_bytes(&bytes).unwrap();
+    drop(bytes);
+
+    // trips on every link, a plan split between routes counting its share on each
+    let mut trips = vec![0.0; graph.edge_count()];
+    for paths in &assignment.paths {
+        for (links, share) in paths {
+            for link in links {
+                trips[*link as usize] += share;
+            }
+        }
+    }
+
+    let links = graph.edge_weights().collect::<Vec<_>>();
+    let ids = links.iter().map(|l| l.edge as i32 + 1).collect::<Vec<_>>();
+    let forwards = links.iter().map(|l| l.forward).collect::<Vec<_>>();
+    let capacities = links.iter().map(|l| l.capacity).collect::<Vec<_>>();
+    let ratios = assignment
+        .volumes
+        .iter()
+        .zip(&capacities)
+        .map(|(v, c)| if *c > 0.0 { v / c } else { 0.0 })
+        .collect::<Vec<_>>();
+
+    println!(
+        "[volume stats] links: {}, loaded: {}, over capacity: {}, max v/c: {:.3}",
+        links.len(),
+        trips.iter().filter(|t| **t > 0.0).count(),
+        ratios.iter().filter(|r| **r > 1.0).count(),
+        ratios.iter().copied().fold(0.0, f64::max)
+    );
+
+    #[rustfmt::skip]
+    let pool = sqlx::postgres::PgPoolOptions::new()
+        .connect("postgres://postgres:0@localhost/postgres")
+        .await
+        .expect("failed to connect postgresql");
+
+    #[rustfmt::skip]
+    sqlx::query("DROP TABLE IF EXISTS volume")
+        .execute(&pool)
+        .await
+        .unwrap();
+
+    #[rustfmt::skip]
+    sqlx::query("CREATE TABLE IF NOT EXISTS volume (id Int4 REFERENCES edge (id), forward Bool, trips Float8, volume Float8, capacity Float8, vc Float8, free Float8, time Float8)")
+        .execute(&pool)
+        .await
+        .unwrap();
+
+    #[rustfmt::skip]
+    sqlx::query("INSERT INTO volume (id, forward, trips, volume, capacity, vc, free, time) SELECT * FROM unnest($1, $2, $3, $4, $5, $6, $7, $8)")
+        .bind(&ids)
+        .bind(&forwards)
+        .bind(&trips)
+        .bind(&assignment.volumes)
+        .bind(&capacities)
+        .bind(&ratios)
+        .bind(&assignment.free)
+        .bind(&assignment.times)
+        .execute(&pool)
+        .await
+        .unwrap();
+
+    if let Some(path) = &cli.csv {
+        let mut text = String::from("id,forward,trips,volume,capacity,vc,free,time\n");
+        for i in 0..links.len() {
+            text += &format!(
+                "{},{},{},{},{},{},{},{}\n",
+                ids[i],
+                forwards[i],
+                trips[i],
+                assignment.volumes[i],
+                capacities[i],
+                ratios[i],
+                assignment.free[i],
+                assignment.times[i]
+            );
+        }
+        std::fs::write(path, text).expect("failed to write csv");
+    }
+
+    if let Some(path) = &cli.geojson {
+        let sql = format!("SELECT v.id, v.forward, v.trips, v.volume, v.vc, v.time, ST_AsGeoJSON({}) FROM volume v JOIN edge e ON e.id = v.id WHERE v.trips > 0 ORDER BY v.id, v.forward DESC", network::link_geom("v.forward"));
+
+        #[rustfmt::skip]
+        let rows: Vec<(i32, bool, f64, f64, f64, f64, String)> = sqlx::query_as(&sql)
+            .fetch_all(&pool)
+            .await
+            .unwrap();
+
+        let features = rows
+            .into_iter()
+            .map(|(id, forward, trips, volume, vc, time, geom)| {
+                let geometry = geom
+                    .parse::<geojson::Geometry>()
+                    .expect("failed to parse geojson");
+
+                let mut properties = geojson::JsonObject::new();
+                properties.insert("id".to_string(), id.into());
+                properties.insert("forward".to_string(), forward.into());
+                properties.insert("trips".to_string(), trips.into());
+                properties.insert("volume".to_string(), volume.into());
+                properties.insert("vc".to_string(), vc.into());
+                properties.insert("time".to_string(), time.into());
+
+                geojson::Feature {
+                    geometry: Some(geometry),
+                    properties: Some(properties),
+                    ..Default::default()
+                }
+            })
+            .collect::<Vec<_>>();
+
+        let collection = geojson::FeatureCollection {
+            bbox: None,
+            features,
+            foreign_members: None,
+        };
+        std::fs::write(path, collection.to_string()).expect("failed to write geojson");
+    }
+}
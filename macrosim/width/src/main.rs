@@ -0,0 +1,110 @@
+use clap::Parser;
+
+/// Measure the road width of every edge against the FGD lines into the `width` table.
+#[derive(Debug, clap::Parser)]
+struct Cli {
+    /// length of each ray in metres
+    #[arg(long, default_value_t = network::width::Options::default().ray, value_parser = area::parse::positive)]
+    ray: f64,
+    /// largest distance between sample points in metres
+    #[arg(long, default_value_t = network::width::Options::default().spacing, value_parser = area::parse::positive)]
+    spacing: f64,
+    /// FGD `type` values treated as road edges
+    #[arg(long = "type", value_delimiter = ',', default_values = ["真幅道路", "庭園路等", "徒歩道"])]
+    types: Vec<String>,
+    /// worker threads, all cores by default
+    #[arg(long)]
+    threads: Option<usize>,
+}
+
+#[tokio::main]
+async fn main() {
+    let cli = Cli::parse();
+
+    #[rustfmt::skip]
+    let pool = sqlx::postgres::PgPoolOptions::new()
+        .connect("postgres://postgres:0@localhost/postgres")
+        .await
+        .expect("failed to connect postgresql");
+
+    #[rustfmt::skip]
+    let edges: Vec<(i32, String)> = sqlx::query_as("SELECT id, ST_AsGeoJSON(geom) FROM edge ORDER BY id")
+        .fetch_all(&pool)
+        .await
+        .unwrap();
+
+    #[rustfmt::skip]
+    let fgd: Vec<(String,)> = sqlx::query_as("SELECT ST_AsGeoJSON(geom) FROM fgd WHERE type = ANY($1)")
+        .bind(&cli.types)
+        .fetch_all(&pool)
+        .await
+        .unwrap();
+
+    let (ids, edges): (Vec<_>, Vec<_>) = edges
+        .into_iter()
+        .map(|(id, geom)| {
+            let line: geo::LineString<f64> = parse(&geom);
+            (id, line.0.iter().map(|c| c.x_y()).collect::<Vec<_>>())
+        })
+        .unzip();
+    let fgd = fgd.iter().map(|(geom,)| parse(geom)).collect::<Vec<_>>();
+
+    println!(
+        "[width stats] edges: {}, fgd lines: {}",
+        edges.len(),
+        fgd.len()
+    );
+
+    let mut options = network::width::Options {
+        ray: cli.ray,
+        spacing: cli.spacing,
+        ..Default::default()
+    };
+    if let Some(threads) = cli.threads {
+        options.threads = threads;
+    }
+
+    let widths = network::width::estimate(&edges, &fgd, &options);
+
+    println!(
+        "[width stats] measured: {}, unmeasured: {}",
+        widths.iter().filter(|w| w.samples > 0).count(),
+        widths.iter().filter(|w| w.samples == 0).count()
+    );
+
+    #[rustfmt::skip]
+    sqlx::query("DROP TABLE IF EXISTS width")
+        .execute(&pool)
+        .await
+        .unwrap();
+
+    // `width` is the median, `samples` the rays which hit an FGD line on either side
+    #[rustfmt::skip]
+    sqlx::query("CREATE TABLE IF NOT EXISTS width (id Int4 REFERENCES edge (id), width Float8, min Float8, max Float8, samples Int4, rays Int4)")
+        .execute(&pool)
+        .await
+        .unwrap();
+
+    let medians = widths.iter().map(|w| w.median).collect::<Vec<_>>();
+    let mins = widths.iter().map(|w| w.min).collect::<Vec<_>>();
+    let maxs = widths.iter().map(|w| w.max).collect::<Vec<_>>();
+    let samples = widths.iter().map(|w| w.samples as i32).collect::<Vec<_>>();
+    let rays = widths.iter().map(|w| w.rays as i32).collect::<Vec<_>>();
+
+    #[rustfmt::skip]
+    sqlx::query("INSERT INTO width (id, width, min, max, samples, rays) SELECT * FROM unnest($1, $2, $3, $4, $5, $6)")
+        .bind(&ids)
+        .bind(&medians)
+        .bind(&mins)
+        .bind(&maxs)
+        .bind(&samples)
+        .bind(&rays)
+        .execute(&pool)
+        .await
+        .unwrap();
+}
+
+fn parse(geom: &str) -> geo::LineString<f64> {
+    let geometry = geom.parse::<geojson::Geometry>().unwrap();
+    geometry.value.try_into().unwrap()
+}
@@ -8,32 +8,73 @@ const MAX_AGENT_COUNT: usize = 10000;
 #[derive(Debug, Clone, Default)]
 struct Agent {
     xy: (f64, f64),
+    /// position of the current link in the path
+    link: usize,
+    /// next point of the current link
     seq: usize,
+    /// steps left waiting out a movement
+    wait: usize,
     shift: usize,
     work: bool,
+    /// the next link of the path is no movement out of the current one
+    stranded: bool,
 }
 
 #[tokio::main]
 async fn main() {
-    type Data = (
-        petgraph::Graph<(f64, f64), (f64, u32), petgraph::Undirected>,
-        Vec<Vec<u32>>,
-    );
+    type Data = (network::Graph, Vec<Vec<u32>>, Vec<i32>);
 
     let bytes = std::fs::read("path.bin").unwrap();
-    let (graph, paths): Data = postcard::from_bytes(&bytes).unwrap();
+    let (graph, paths, _): Data = postcard::from_bytes(&bytes).unwrap();
+    drop(bytes);
+
+    let bytes = std::fs::read("turn.bin").unwrap();
+    let turns: network::turn::Turns = postcard::from_bytes(&bytes).unwrap();
     drop(bytes);
 
+    #[rustfmt::skip]
+    let pool = sqlx::postgres::PgPoolOptions::new()
+        .connect("postgres://postgres:0@localhost/postgres")
+        .await
+        .expect("failed to connect postgresql");
+
+    #[rustfmt::skip]
+    let edges: Vec<(i32, Vec<f64>, Vec<f64>)> = sqlx::query_as("SELECT id, ARRAY(SELECT ST_X(geom) FROM ST_DumpPoints(edge.geom) ORDER BY path), ARRAY(SELECT ST_Y(geom) FROM ST_DumpPoints(edge.geom) ORDER BY path) FROM edge")
+        .fetch_all(&pool)
+        .await
+        .unwrap();
+
+    // road shape of every edge from `n1` to `n2`
+    let shapes = edges
+        .into_iter()
+        .map(|(id, xs, ys)| (id as u32 - 1, xs.into_iter().zip(ys).collect::<Vec<_>>()))
+        .collect::<std::collections::HashMap<_, _>>();
+
     let indicator = indicatif::ProgressBar::new(MAX_STEP_COUNT as u64);
 
     let mut rng = rand::rngs::StdRng::seed_from_u64(SEED);
-    let paths = paths.into_iter().choose_multiple(&mut rng, MAX_AGENT_COUNT);
+    let paths = paths
+        .into_iter()
+        .filter(|path| !path.is_empty())
+        .choose_multiple(&mut rng, MAX_AGENT_COUNT);
+
+    // points of every travelled link from its start to its end
+    let mut coords = std::collections::HashMap::new();
+    for &link in paths.iter().flatten() {
+        coords.entry(link).or_insert_with(|| {
+            let index = petgraph::graph::EdgeIndex::new(link as usize);
+            let link = &graph[index];
+            let shape = &shapes[&link.edge];
+            match link.forward {
+                true => shape.clone(),
+                false => shape.iter().rev().copied().collect::<Vec<_>>(),
+            }
+        });
+    }
 
     let mut agents = vec![Agent::default(); paths.len()];
     for i in 0..agents.len() {
-        agents[i].xy = *graph
-            .node_weight(petgraph::graph::NodeIndex::new(paths[i][0] as usize))
-            .unwrap();
+        agents[i].xy = coords[&paths[i][0]][0];
 
         agents[i].shift = rand::distributions::Uniform::new(0, MAX_STEP_COUNT).sample(&mut rng);
     }
@@ -45,18 +86,40 @@ async fn main() {
                 continue;
             }
 
-            if agents[i].seq >= paths[i].len() {
-                agents[i].work = false;
-                continue;
+            if agents[i].seq >= coords[&paths[i][agents[i].link]].len() {
+                let link = paths[i][agents[i].link];
+                let next = match paths[i].get(agents[i].link + 1) {
+                    Some(inner) => *inner,
+                    None => {
+                        agents[i].work = false;
+                        continue;
+                    }
+                };
+
+                // into the next link by a movement out of this one, its penalty in seconds waited
+                let penalty = match turns.next[link as usize].iter().find(|(l, _)| *l == next) {
+                    Some((_, penalty)) => *penalty,
+                    None => {
+                        agents[i].stranded = true;
+                        agents[i].work = false;
+                        continue;
+                    }
+                };
+
+                agents[i].link += 1;
+                // the first point is the end of the previous link
+                agents[i].seq = 1;
+                agents[i].wait = penalty.round() as usize;
             }
 
-            let n1 = {
-                let index = paths[i][agents[i].seq as usize] as usize;
-                petgraph::graph::NodeIndex::new(index)
-            };
+            if agents[i].wait > 0 {
+                agents[i].wait -= 1;
+                agents[i].work = true;
+                continue;
+            }
 
             let (x0, y0) = agents[i].xy;
-            let (x1, y1) = *graph.node_weight(n1).unwrap();
+            let (x1, y1) = coords[&paths[i][agents[i].link]][agents[i].seq];
 
             let p0 = geo::Point::new(x0, y0);
             let p1 = geo::Point::new(x1, y1);
@@ -81,11 +144,11 @@ async fn main() {
     indicator.finish();
     println!("{}", indicator.elapsed().as_secs_f64());
 
-    #[rustfmt::skip]
-    let pool = sqlx::postgres::PgPoolOptions::new()
-        .connect("postgres://postgres:0@localhost/postgres")
-        .await
-        .expect("failed to connect postgresql");
+    println!(
+        "[agent stats] agents: {}, stranded: {}",
+        agents.len(),
+        agents.iter().filter(|agent| agent.stranded).count()
+    );
 
     #[rustfmt::skip]
     sqlx::query("DROP TABLE IF EXISTS agent")
@@ -1,20 +1,49 @@
+import pathlib
+import sys
+import tomllib
+
 import geopandas as gpd
 import sqlalchemy
 import shapely.geometry
 
+# study area shared with the crawlers, area.toml here or in the nearest parent like they do
+if len(sys.argv) > 1:
+    config_path = pathlib.Path(sys.argv[1])
+else:
+    cwd = pathlib.Path.cwd()
+    config_path = next(
+        (d / "area.toml" for d in [cwd, *cwd.parents] if (d / "area.toml").is_file()), None
+    )
+    if config_path is None:
+        raise FileNotFoundError("no area.toml in the working directory or its parents")
+with open(config_path, "rb") as f:
+    area = tomllib.load(f)["area"]
+
+# paths in the config are relative to it
+if "geojson" in area:
+    area["geojson"] = config_path.parent / area["geojson"]
+
+engine = sqlalchemy.create_engine("postgresql://postgres:0@localhost:5432/postgres")
+
+if "bbox" in area:
+    x_min, y_min, x_max, y_max = area["bbox"]
+    region = shapely.geometry.box(x_min, y_min, x_max, y_max)
+elif "geojson" in area:
+    region = gpd.read_file(area["geojson"]).to_crs(6668).union_all()
+elif "city" in area:
+    sql = sqlalchemy.text('SELECT geom FROM city WHERE "N03_007" = :code')
+    region = gpd.read_postgis(sql, engine, params={"code": area["city"]}).union_all()
+else:
+    raise ValueError("area needs one of bbox, geojson or city")
+
 # gdf = gpd.read_file("Mesh4_POP_00.shp", engine="pyogrio")
 gdf = gpd.read_file("Mesh4_POP_16.shp", engine="pyogrio")
 
 gdf.rename_geometry("geom", inplace=True)
 gdf.to_crs(6668, inplace=True)
 
-x_min, y_min = 137.011029079, 36.646053135
-x_max, y_max = 137.180130220, 36.793910577
-region = shapely.geometry.Polygon([(x_min, y_min), (x_max, y_min), (x_max, y_max), (x_min, y_max)])
-
 gdf = gdf[gdf.intersects(region)]
 print(gdf)
 
 # # insert to db
-engine = sqlalchemy.create_engine("postgresql://postgres:0@localhost:5432/postgres")
 gdf.to_postgis("population", engine)